@@ -4,7 +4,11 @@
 import * as vscode from 'vscode';
 import * as cedar from 'vscode-cedar-wasm';
 import * as jsonc from 'jsonc-parser';
-import { parseCedarEntitiesDoc, parseCedarSchemaDoc } from './parser';
+import {
+  parseCedarEntitiesDoc,
+  parseCedarPoliciesDoc,
+  parseCedarSchemaDoc,
+} from './parser';
 const SOURCE_CEDAR = 'Cedar';
 export const DEFAULT_RANGE = new vscode.Range(
   new vscode.Position(0, 0),
@@ -14,7 +18,6 @@ import {
   EXIST_ATTR_REGEX,
   EXPECTED_ATTR_REGEX,
   MISMATCH_ATTR_REGEX,
-  OFFSET_POLICY_REGEX,
   AT_LINE_SCHEMA_REGEX,
   UNRECOGNIZED_REGEX,
   EXPECTED_ATTR2_REGEX,
@@ -49,85 +52,28 @@ const addDiagnosticsError = (
   diagnostics.push(diagnostic);
 };
 
-const determineRangeFromPolicyMessage = (
-  vpm: cedar.ValidateMessage,
-  policy: string,
-  defaultErrorRange: vscode.Range,
-  startLine: number
-): vscode.Range => {
-  let range = defaultErrorRange;
-  let startCharacter = vpm.offset;
-  let endCharacter = vpm.offset + vpm.length;
-
-  // TODO: investigate if this still is a valid path
-  let found = vpm.message.match(OFFSET_POLICY_REGEX);
-  if (found?.groups) {
-    startCharacter = parseInt(found?.groups.start);
-    endCharacter = parseInt(found?.groups.end) || startCharacter;
-  }
-
-  if (startCharacter > 0 && endCharacter > 0) {
-    const lines = policy.split('\n');
-    let lineStart = 0;
-    // not efficient, but Cedar policies are small
-    for (let i = 0; i < lines.length; i++) {
-      const lineEnd = lines[i].length;
-      if (
-        lineStart + 1 <= startCharacter &&
-        lineStart + 1 + lineEnd >= endCharacter
-      ) {
-        range = new vscode.Range(
-          new vscode.Position(startLine + i, startCharacter - lineStart),
-          new vscode.Position(startLine + i, endCharacter - lineStart)
-        );
-        break;
-      }
-      lineStart += lineEnd + 1;
-    }
-  }
-  return range;
+// messages from wasm have zero-based UTF-16 positions, unless they were
+// reported without a span
+const hasSpan = (e: cedar.ValidateMessage): boolean => {
+  return e.offset > 0 || e.length > 0;
 };
 
-export const determineRangeFromOffset = (
-  document: vscode.TextDocument,
-  offset: number,
-  length: number
-): vscode.Range => {
-  let range = DEFAULT_RANGE;
-  const startCharacter = offset;
+const rangeFromMessage = (e: cedar.ValidateMessage): vscode.Range => {
+  const start = new vscode.Position(e.startLine, e.startCharacter);
+  const end = new vscode.Position(e.endLine, e.endCharacter);
   // "invalid token" is 0 length, make range at least 1 character
-  const endCharacter = offset + Math.max(length, 1);
-  let lineStart = 0;
-  // not efficient, but Cedar documents are small
-  for (let i = 0; i < document.lineCount; i++) {
-    const lineEnd = document.lineAt(i).text.length;
-    if (
-      lineStart + 1 <= startCharacter &&
-      lineStart + 1 + lineEnd >= endCharacter
-    ) {
-      range = new vscode.Range(
-        new vscode.Position(i, startCharacter - lineStart),
-        new vscode.Position(i, endCharacter - lineStart)
-      );
-      break;
-    }
-    lineStart += lineEnd + 1;
-  }
-  return range;
+  return new vscode.Range(start, e.length > 0 ? end : start.translate(0, 1));
 };
 
 const determineRangeFromError = (
-  vse: {
-    message: string;
-    offset: number;
-    length: number;
-  },
+  vse: cedar.ValidateMessage,
+  message: string,
   document: vscode.TextDocument
 ): { error: string; range: vscode.Range } => {
-  let error = vse.message;
+  let error = message;
   let range = DEFAULT_RANGE;
-  if (vse.offset > 0) {
-    range = determineRangeFromOffset(document, vse.offset, vse.length);
+  if (hasSpan(vse)) {
+    range = rangeFromMessage(vse);
   } else {
     const found = error.match(AT_LINE_SCHEMA_REGEX);
     if (found) {
@@ -403,11 +349,7 @@ const handleEntitiesDiagnosticError = (
 
 export const addSyntaxDiagnosticErrors = (
   diagnostics: vscode.Diagnostic[],
-  errors: Array<{
-    message: string;
-    offset: number;
-    length: number;
-  }>,
+  errors: Array<cedar.ValidateMessage>,
   document: vscode.TextDocument
 ) => {
   // create an error for each of the syntax validator errors
@@ -419,6 +361,11 @@ export const addSyntaxDiagnosticErrors = (
     ) {
       e = e.substring(e.indexOf(': ') + 2);
 
+      // errors located in the entities JSON by wasm already have a range
+      if (vse.length > 0) {
+        addDiagnosticsError(diagnostics, rangeFromMessage(vse), e);
+        return;
+      }
       if (handleEntitiesDiagnosticError(diagnostics, document, e)) {
         return;
       }
@@ -446,7 +393,6 @@ export const addSyntaxDiagnosticErrors = (
       };
       const undeclaredType =
         mappings[found.groups.type as keyof typeof mappings];
-      // let range = determineRangeFromOffset(document, vse.offset, vse.length);
       let endOfDocRange = addUndeclaredDiagnosticErrors(
         diagnostics,
         document,
@@ -458,7 +404,7 @@ export const addSyntaxDiagnosticErrors = (
     }
 
     // defend against future parse errors including the range
-    if (vse.offset === 0 && vse.length === 0) {
+    if (!hasSpan(vse)) {
       found = e.match(PARSE_ERROR_SCHEMA_REGEX);
       if (found?.groups && found?.groups.type) {
         let range = rangeFromParseError(document, found?.groups.type);
@@ -467,10 +413,7 @@ export const addSyntaxDiagnosticErrors = (
       }
     }
 
-    const { error, range } = determineRangeFromError(
-      { ...vse, message: e },
-      document
-    );
+    const { error, range } = determineRangeFromError(vse, e, document);
     if (
       error === 'EOF while parsing a value' &&
       document.getText().trim() === ''
@@ -482,6 +425,16 @@ export const addSyntaxDiagnosticErrors = (
   });
 };
 
+// errors located by wasm, with zero-based UTF-16 positions
+export const addPositionedDiagnosticErrors = (
+  diagnostics: vscode.Diagnostic[],
+  errors: Array<cedar.ValidateMessage>
+) => {
+  errors.forEach((e) => {
+    addDiagnosticsError(diagnostics, rangeFromMessage(e), e.message);
+  });
+};
+
 export const addValidationDiagnosticInfo = (
   diagnostics: vscode.Diagnostic[],
   info: string
@@ -495,6 +448,19 @@ export const addValidationDiagnosticInfo = (
   diagnostics.push(diagnostic);
 };
 
+export const addValidationDiagnosticWarnings = (
+  diagnostics: vscode.Diagnostic[],
+  warnings: Array<cedar.ValidateMessage>
+) => {
+  warnings.forEach((w) => {
+    addValidationDiagnosticWarning(
+      diagnostics,
+      w.message,
+      hasSpan(w) ? rangeFromMessage(w) : DEFAULT_RANGE
+    );
+  });
+};
+
 export const addValidationDiagnosticWarning = (
   diagnostics: vscode.Diagnostic[],
   message: string,
@@ -512,21 +478,14 @@ export const addValidationDiagnosticWarning = (
 export const addPolicyResultMessages = (
   diagnostics: vscode.Diagnostic[],
   messages: cedar.ValidateMessage[],
-  policy: string,
-  effectRange: vscode.Range,
-  startLine: number,
+  cedarDoc: vscode.TextDocument,
   areWarnings: boolean
 ) => {
   // create an error for each of the errors
   messages.forEach((vpm) => {
     let e = vpm.message;
     let diagnosticCode = undefined;
-    let range = determineRangeFromPolicyMessage(
-      vpm,
-      policy,
-      effectRange,
-      startLine
-    );
+    let range = hasSpan(vpm) ? rangeFromMessage(vpm) : DEFAULT_RANGE;
     if (
       e.startsWith('validation error on policy `policy0`') ||
       e.startsWith('validation error on `policy `policy0`')
@@ -540,20 +499,25 @@ export const addPolicyResultMessages = (
 
     let found = e.match(UNRECOGNIZED_REGEX);
     if (found?.groups && found?.groups.unrecognized) {
-      const lines = policy.split('\n');
+      // search the policy the message is in, or the whole document
+      const policyRange = parseCedarPoliciesDoc(cedarDoc).policies.find(
+        (policy) => policy.range.contains(range.start)
+      )?.range;
+      const startLine = policyRange ? policyRange.start.line : 0;
+      const endLine = policyRange ? policyRange.end.line : cedarDoc.lineCount - 1;
       // not efficient, but Cedar policies are small
-      for (let i = 0; i < lines.length; i++) {
+      for (let i = startLine; i <= endLine; i++) {
         // unrecognized Actions end in "
         const suffix = found?.groups.unrecognized.endsWith('"') ? '' : '::';
-        const startCharacter = lines[i].indexOf(
-          found?.groups.unrecognized + suffix
-        );
+        const startCharacter = cedarDoc
+          .lineAt(i)
+          .text.indexOf(found?.groups.unrecognized + suffix);
         if (startCharacter > -1) {
           const endCharacter =
             startCharacter + found?.groups.unrecognized.length;
           range = new vscode.Range(
-            new vscode.Position(startLine + i, startCharacter),
-            new vscode.Position(startLine + i, endCharacter)
+            new vscode.Position(i, startCharacter),
+            new vscode.Position(i, endCharacter)
           );
 
           diagnosticCode = 'unrecognized';
@@ -7,6 +7,8 @@ import {
   parseCedarPoliciesDoc,
   parseCedarSchemaDoc,
   parseCedarTemplateLinksDoc,
+  parsePolicyOutline,
+  sourceRange,
 } from './parser';
 
 export class CedarDocumentSymbolProvider
@@ -18,6 +20,43 @@ export class CedarDocumentSymbolProvider
   ): Promise<vscode.DocumentSymbol[]> {
     try {
       const symbols: vscode.DocumentSymbol[] = [];
+      const outline = parsePolicyOutline(document);
+      if (outline) {
+        outline.forEach((policy) => {
+          const symbol = new vscode.DocumentSymbol(
+            policy.policyId,
+            policy.kind === 'template' ? 'template' : '',
+            vscode.SymbolKind.Function,
+            sourceRange(policy.range),
+            sourceRange(policy.effectRange)
+          );
+          policy.scope.forEach((scope) => {
+            symbol.children.push(
+              new vscode.DocumentSymbol(
+                scope.constraint,
+                '',
+                vscode.SymbolKind.Variable,
+                sourceRange(scope.range),
+                sourceRange(scope.range)
+              )
+            );
+          });
+          policy.conditions.forEach((condition) => {
+            symbol.children.push(
+              new vscode.DocumentSymbol(
+                condition.kind,
+                '',
+                vscode.SymbolKind.Boolean,
+                sourceRange(condition.range),
+                sourceRange(condition.range)
+              )
+            );
+          });
+          symbols.push(symbol);
+        });
+        return Promise.resolve(symbols);
+      }
+
       const policyRanges = parseCedarPoliciesDoc(document).policies;
       policyRanges.forEach((policyRange, index) => {
         symbols.push(
@@ -45,6 +84,38 @@ export class CedarFoldingRangeProvider implements vscode.FoldingRangeProvider {
     token: vscode.CancellationToken
   ): vscode.FoldingRange[] {
     const ranges: vscode.FoldingRange[] = [];
+    const outline = parsePolicyOutline(document);
+    if (outline) {
+      outline.forEach((policy) => {
+        const range = sourceRange(policy.range);
+        const effectRange = sourceRange(policy.effectRange);
+        ranges.push(
+          new vscode.FoldingRange(
+            range.start.line,
+            range.end.line,
+            vscode.FoldingRangeKind.Region
+          )
+        );
+        if (effectRange.start.line > range.start.line) {
+          ranges.push(
+            new vscode.FoldingRange(effectRange.start.line, range.end.line)
+          );
+        }
+        policy.conditions.forEach((condition) => {
+          const conditionRange = sourceRange(condition.range);
+          if (conditionRange.end.line > conditionRange.start.line) {
+            ranges.push(
+              new vscode.FoldingRange(
+                conditionRange.start.line,
+                conditionRange.end.line
+              )
+            );
+          }
+        });
+      });
+      return ranges;
+    }
+
     const policyRanges = parseCedarPoliciesDoc(document).policies;
     policyRanges.forEach((policyRange, index) => {
       ranges.push(
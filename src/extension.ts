@@ -21,9 +21,14 @@ import {
   saveTextAndFormat,
 } from './fileutil';
 import { createDiagnosticCollection } from './diagnostics';
-import { formatCedarDoc, formatCedarSchemaDoc } from './format';
+import {
+  formatCedarDoc,
+  formatCedarDocRange,
+  formatCedarSchemaDoc,
+} from './format';
 import {
   clearValidationCache,
+  handleDidCloseTextDocument,
   validateCedarDoc,
   validateEntitiesDoc,
   validateSchemaDoc,
@@ -185,6 +190,13 @@ export async function activate(context: vscode.ExtensionContext) {
           // no need to await to invoke vscode.window.showErrorMessage
           getSchemaTextDocument();
         }
+        if (e.affectsConfiguration('cedar.validationMode')) {
+          // cached results were computed with the previous mode
+          clearValidationCache();
+          vscode.workspace.textDocuments.forEach((document) =>
+            validateTextDocument(document, diagnosticCollection)
+          );
+        }
       }
     )
   );
@@ -201,6 +213,12 @@ export async function activate(context: vscode.ExtensionContext) {
     )
   );
 
+  context.subscriptions.push(
+    vscode.workspace.onDidCloseTextDocument((document) =>
+      handleDidCloseTextDocument(document)
+    )
+  );
+
   context.subscriptions.push(
     vscode.window.onDidChangeActiveTextEditor((editor) => {
       if (editor) {
@@ -314,15 +332,16 @@ export async function activate(context: vscode.ExtensionContext) {
               /\.cedar$/,
               `(${result.label}).cedar.json`
             );
-            const exportJson = await exportCedarDocPolicyById(
+            const { json: exportJson, errors } = await exportCedarDocPolicyById(
               cedarDoc,
               result.label,
               exportFilename
             );
 
             if (!exportJson) {
+              const reason = errors.length > 0 ? `: ${errors[0]}` : '';
               vscode.window.showErrorMessage(
-                `Unable to export Cedar policy: ${result.label}`
+                `Unable to export Cedar policy: ${result.label}${reason}`
               );
             } else if (results.length === 1) {
               vscode.commands.executeCommand(
@@ -534,20 +553,25 @@ export async function activate(context: vscode.ExtensionContext) {
           return Promise.resolve(undefined);
         }
 
-        const formattedPolicy = formatCedarDoc(cedarDoc);
-        if (formattedPolicy) {
-          // use replacement Range of full document
-          const policyRange = new vscode.Range(
-            cedarDoc.lineAt(0).range.start,
-            cedarDoc.lineAt(cedarDoc.lineCount - 1).range.end
-          );
-
-          return Promise.resolve([
-            vscode.TextEdit.replace(policyRange, formattedPolicy),
-          ]);
+        // edits only where the formatted text differs
+        return Promise.resolve(formatCedarDoc(cedarDoc) || undefined);
+      },
+    })
+  );
+  context.subscriptions.push(
+    vscode.languages.registerDocumentRangeFormattingEditProvider('cedar', {
+      async provideDocumentRangeFormattingEdits(
+        cedarDoc: vscode.TextDocument,
+        range: vscode.Range
+      ): Promise<vscode.TextEdit[] | undefined> {
+        // don't try and format if syntax doesn't validate
+        if (!(await validateCedarDoc(cedarDoc, diagnosticCollection))) {
+          return Promise.resolve(undefined);
         }
 
-        return Promise.resolve(undefined); // no edits
+        return Promise.resolve(
+          formatCedarDocRange(cedarDoc, range) || undefined
+        );
       },
     })
   );
@@ -561,20 +585,8 @@ export async function activate(context: vscode.ExtensionContext) {
           return Promise.resolve(undefined);
         }
 
-        const formattedSchema = formatCedarSchemaDoc(schemaDoc);
-        if (formattedSchema) {
-          // use replacement Range of full document
-          const policyRange = new vscode.Range(
-            schemaDoc.lineAt(0).range.start,
-            schemaDoc.lineAt(schemaDoc.lineCount - 1).range.end
-          );
-
-          return Promise.resolve([
-            vscode.TextEdit.replace(policyRange, formattedSchema),
-          ]);
-        }
-
-        return Promise.resolve(undefined); // no edits
+        // edits only where the formatted text differs
+        return Promise.resolve(formatCedarSchemaDoc(schemaDoc) || undefined);
       },
     })
   );
@@ -14,7 +14,13 @@ const CEDAR_ENTITIES_FILE = `cedarentities.json`;
 const CEDAR_ENTITIES_EXTENSION_JSON = `.cedarentities.json`;
 export const CEDAR_ENTITIES_GLOB = `{**/cedarentities.json,**/*.cedarentities.json,**/avpentities.json,**/*.avpentities.json}`;
 
+const CEDAR_TEMPLATELINKS_FILES = [
+  `cedartemplatelinks.json`,
+  `cedarlinks.json`,
+];
 export const CEDAR_TEMPLATELINKS_GLOB = `{**/cedartemplatelinks.json,**/*.cedartemplatelinks.json,**/cedarlinks.json,**/*.cedarlinks.json}`;
+const CEDAR_POLICIES_EXTENSION = `.cedar`;
+const CEDAR_AUTH_FILES = [`cedarauth.json`, `cedarparc.json`];
 export const CEDAR_AUTH_GLOB = `{**/cedarauth.json,**/*.cedarauth.json,**/cedarparc.json,**/*.cedarparc.json}`;
 export const CEDAR_JSON_GLOB = `**/*.cedar.json`;
 
@@ -35,6 +41,22 @@ export const detectEntitiesDoc = (doc: vscode.TextDocument): boolean => {
   return result;
 };
 
+export const detectAuthDoc = (doc: vscode.TextDocument): boolean => {
+  return CEDAR_AUTH_FILES.some(
+    (file) =>
+      doc.fileName.endsWith(path.sep + file) ||
+      doc.fileName.endsWith(`.${file}`)
+  );
+};
+
+export const detectTemplateLinksDoc = (doc: vscode.TextDocument): boolean => {
+  return CEDAR_TEMPLATELINKS_FILES.some(
+    (file) =>
+      doc.fileName.endsWith(path.sep + file) ||
+      doc.fileName.endsWith(`.${file}`)
+  );
+};
+
 const findSchemaFilesInFolder = async (filepath: string): Promise<string[]> => {
   const schemaFiles = new Set<string>();
   const files = await vscode.workspace.fs.readDirectory(
@@ -168,6 +190,28 @@ export const getSchemaTextDocument = async (
   return Promise.resolve(schemaDoc);
 };
 
+// template links refer to the templates of the only Cedar policies file in
+// the same folder
+export const getTemplatesTextDocument = async (
+  linksDoc: vscode.TextDocument
+): Promise<vscode.TextDocument | undefined> => {
+  const folder = path.dirname(linksDoc.uri.fsPath);
+  const files = (
+    await vscode.workspace.fs.readDirectory(vscode.Uri.file(folder))
+  ).filter(
+    (file) =>
+      file[1] === vscode.FileType.File &&
+      file[0].endsWith(CEDAR_POLICIES_EXTENSION)
+  );
+  if (files.length !== 1) {
+    return Promise.resolve(undefined);
+  }
+
+  return vscode.workspace.openTextDocument(
+    vscode.Uri.file(path.join(folder, files[0][0]))
+  );
+};
+
 const getJsonIndentSize = (): number => {
   const config = vscode.workspace.getConfiguration('editor', {
     languageId: 'json',
@@ -5,10 +5,25 @@ import * as vscode from 'vscode';
 import * as cedar from 'vscode-cedar-wasm';
 import { scanLeadingComments } from './diagnostics';
 
+const textEdits = (edits: cedar.TextEdit[]): vscode.TextEdit[] => {
+  return edits.map(
+    (edit) =>
+      new vscode.TextEdit(
+        new vscode.Range(
+          edit.range.startLine,
+          edit.range.startCharacter,
+          edit.range.endLine,
+          edit.range.endCharacter
+        ),
+        edit.newText
+      )
+  );
+};
+
 export const formatCedarDoc = (
   cedarDoc: vscode.TextDocument
-): string | null => {
-  let formattedPolicy = null;
+): vscode.TextEdit[] | null => {
+  let formatEdits = null;
   const { skipFormatting } = scanLeadingComments(cedarDoc);
 
   if (!skipFormatting) {
@@ -23,19 +38,75 @@ export const formatCedarDoc = (
       wordWrapColumn,
       tabSize
     );
-    if (formatResult.success) {
-      formattedPolicy = formatResult.policy as string;
+    if (formatResult.success && formatResult.edits) {
+      formatEdits = textEdits(formatResult.edits);
+    }
+    formatResult.free();
+  }
+
+  return formatEdits;
+};
+
+// wasm works with byte offsets into the UTF-8 text
+const byteOffset = (text: string, offset: number): number => {
+  return new TextEncoder().encode(text.substring(0, offset)).length;
+};
+
+export const formatCedarDocRange = (
+  cedarDoc: vscode.TextDocument,
+  range: vscode.Range
+): vscode.TextEdit[] | null => {
+  let formatEdits = null;
+  const { skipFormatting } = scanLeadingComments(cedarDoc);
+
+  if (!skipFormatting) {
+    const editorConfig = vscode.workspace.getConfiguration('editor', {
+      languageId: 'cedar',
+    });
+    const tabSize = editorConfig.get<number>('tabSize', 2);
+    const wordWrapColumn = editorConfig.get<number>('wordWrapColumn', 80);
+
+    const text = cedarDoc.getText();
+    const formatResult: cedar.FormatPolicyRangeResult =
+      cedar.formatPolicyRange(
+        text,
+        byteOffset(text, cedarDoc.offsetAt(range.start)),
+        byteOffset(text, cedarDoc.offsetAt(range.end)),
+        wordWrapColumn,
+        tabSize
+      );
+    if (formatResult.success && formatResult.edits) {
+      formatEdits = textEdits(formatResult.edits);
     }
     formatResult.free();
   }
 
-  return formattedPolicy;
+  return formatEdits;
 };
 
 export const formatCedarSchemaDoc = (
   schemaDoc: vscode.TextDocument
-): string | null => {
-  // TODO: implement after https://github.com/cedar-policy/cedar/issues/682
+): vscode.TextEdit[] | null => {
+  let formatEdits = null;
+  const { skipFormatting } = scanLeadingComments(schemaDoc);
+
+  if (!skipFormatting) {
+    const editorConfig = vscode.workspace.getConfiguration('editor', {
+      languageId: 'cedarschema',
+    });
+    const tabSize = editorConfig.get<number>('tabSize', 2);
+    const wordWrapColumn = editorConfig.get<number>('wordWrapColumn', 80);
+
+    const formatResult: cedar.FormatSchemaResult = cedar.formatSchema(
+      schemaDoc.getText(),
+      wordWrapColumn,
+      tabSize
+    );
+    if (formatResult.success && formatResult.edits) {
+      formatEdits = textEdits(formatResult.edits);
+    }
+    formatResult.free();
+  }
 
-  return null;
+  return formatEdits;
 };
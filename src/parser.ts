@@ -294,6 +294,27 @@ export const cedarTokensProvider: vscode.DocumentSemanticTokensProvider = {
   },
 };
 
+export const sourceRange = (range: cedar.SourceRange): vscode.Range => {
+  return new vscode.Range(
+    range.startLine,
+    range.startCharacter,
+    range.endLine,
+    range.endCharacter,
+  );
+};
+
+// outline from the Cedar parser, undefined when the policies don't parse
+export const parsePolicyOutline = (
+  cedarDoc: vscode.TextDocument,
+): cedar.PolicyOutline[] | undefined => {
+  const result: cedar.PolicyOutlineResult = cedar.parsePolicyOutline(
+    cedarDoc.getText(),
+  );
+  const policies = result.success ? result.policies : undefined;
+  result.free();
+  return policies;
+};
+
 /*
  * Cedar policy (JSON)
  */
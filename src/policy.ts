@@ -3,15 +3,33 @@
 
 import * as vscode from 'vscode';
 import * as cedar from 'vscode-cedar-wasm';
-import { parseCedarPoliciesDoc } from './parser';
+import {
+  parseCedarPoliciesDoc,
+  parsePolicyOutline,
+  sourceRange,
+} from './parser';
+
+// policies from the Cedar parser, or from the regex parser when the
+// policies don't parse
+const policyRanges = (
+  cedarDoc: vscode.TextDocument
+): { id: string; range: vscode.Range }[] => {
+  const outline = parsePolicyOutline(cedarDoc);
+  if (outline) {
+    return outline.map((policy) => ({
+      id: policy.policyId,
+      range: sourceRange(policy.range),
+    }));
+  }
+  return parseCedarPoliciesDoc(cedarDoc).policies;
+};
 
 export const getPolicyQuickPickItems = (
   cedarDoc: vscode.TextDocument,
   selection: vscode.Range
 ): vscode.QuickPickItem[] => {
   const items: vscode.QuickPickItem[] = [];
-  const policyRanges = parseCedarPoliciesDoc(cedarDoc).policies;
-  policyRanges.forEach((policyRange, index) => {
+  policyRanges(cedarDoc).forEach((policyRange, index) => {
     const item: vscode.QuickPickItem = {
       label: policyRange.id,
       detail: cedarDoc.uri.path
@@ -31,10 +49,10 @@ export const exportCedarDocPolicyById = async (
   cedarDoc: vscode.TextDocument,
   policyId: string,
   exportFilename: string
-): Promise<string> => {
+): Promise<{ json: string; errors: string[] }> => {
   let exportJson = '';
-  const policyRanges = parseCedarPoliciesDoc(cedarDoc).policies;
-  policyRanges.forEach((policyRange, index) => {
+  const errors: string[] = [];
+  policyRanges(cedarDoc).forEach((policyRange, index) => {
     if (policyRange.id === policyId) {
       const rawPolicy = cedarDoc.getText(policyRange.range);
       const isTemplate =
@@ -53,12 +71,16 @@ export const exportCedarDocPolicyById = async (
           vscode.Uri.file(exportFilename),
           new Uint8Array(Buffer.from(exportJson))
         );
+      } else if (exportResult.errors) {
+        exportResult.errors.forEach((e) => {
+          // first line only, the rest is the label and help for the span
+          errors.push(e.message.split('\n')[0]);
+        });
       }
 
       exportResult.free();
-      return Promise.resolve(exportJson);
     }
   });
 
-  return Promise.resolve(exportJson);
+  return Promise.resolve({ json: exportJson, errors: errors });
 };
@@ -13,8 +13,6 @@ export const PARSE_ERROR_SCHEMA_REGEX =
   /(P|p)arse error in (?<type>(entity type|common type|namespace))( identifier)?: /;
 export const AT_LINE_SCHEMA_REGEX =
   / at line (?<line>(\d)+)? column (?<column>(\d)+)?/;
-export const OFFSET_POLICY_REGEX =
-  / at offset (?<start>(\d)+)(-)?(?<end>(\d)+)?: /;
 export const UNRECOGNIZED_REGEX =
   /unrecognized (action|entity type) `(?<unrecognized>.+)`(\ndid you mean `(?<suggestion>.+)`\?)?/m;
 // export const UNDECLARED_REGEX_OLD =
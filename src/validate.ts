@@ -5,15 +5,18 @@ import * as vscode from 'vscode';
 import * as cedar from 'vscode-cedar-wasm';
 import {
   addPolicyResultMessages,
+  addPositionedDiagnosticErrors,
   addSyntaxDiagnosticErrors,
-  addValidationDiagnosticWarning,
-  determineRangeFromOffset,
+  addValidationDiagnosticWarnings,
   reportFormatterOff,
 } from './diagnostics';
 import {
   getSchemaTextDocument,
+  getTemplatesTextDocument,
+  detectAuthDoc,
   detectEntitiesDoc,
   detectSchemaDoc,
+  detectTemplateLinksDoc,
 } from './fileutil';
 import { parseCedarPoliciesDoc } from './parser';
 
@@ -168,8 +171,54 @@ export const fetchEntityTypes = (
   return validationCache.fetchEntityTypes(schemaDoc);
 };
 const validationCache = new ValidationCache();
+
+// parsed schemas, policies and entities are kept in wasm so validating a
+// policies or entities file doesn't re-parse the schema text
+const cedarWorkspace = new cedar.CedarWorkspace();
+const workspaceSchemaVersions: Record<string, number> = {};
+const workspacePoliciesUris = new Set<string>();
+const workspaceEntitiesUris = new Set<string>();
+const updateWorkspaceSchema = (
+  schemaDoc: vscode.TextDocument
+): cedar.ValidateSchemaResult => {
+  const uri = schemaDoc.uri.toString();
+  workspaceSchemaVersions[uri] = schemaDoc.version;
+  if (schemaDoc.languageId === 'cedarschema') {
+    return cedarWorkspace.updateSchemaCedar(uri, schemaDoc.getText());
+  } else {
+    return cedarWorkspace.updateSchemaJSON(uri, schemaDoc.getText());
+  }
+};
+const ensureWorkspaceSchema = (schemaDoc: vscode.TextDocument): string => {
+  const uri = schemaDoc.uri.toString();
+  if (workspaceSchemaVersions[uri] !== schemaDoc.version) {
+    updateWorkspaceSchema(schemaDoc).free();
+  }
+  return uri;
+};
+
+const removeFromWorkspace = (uri: string) => {
+  cedarWorkspace.remove(uri);
+  delete workspaceSchemaVersions[uri];
+  workspacePoliciesUris.delete(uri);
+  workspaceEntitiesUris.delete(uri);
+};
+
 export const clearValidationCache = () => {
   validationCache.clear();
+  for (const uri of Object.keys(workspaceSchemaVersions)) {
+    removeFromWorkspace(uri);
+  }
+  for (const uri of workspacePoliciesUris) {
+    removeFromWorkspace(uri);
+  }
+  for (const uri of workspaceEntitiesUris) {
+    removeFromWorkspace(uri);
+  }
+};
+
+export const handleDidCloseTextDocument = (document: vscode.TextDocument) => {
+  removeFromWorkspace(document.uri.toString());
 };
 export const narrowEntityTypes = (
   schemaDoc: vscode.TextDocument,
@@ -210,6 +259,10 @@ export const validateTextDocument = (
     validateSchemaDoc(doc, diagnosticCollection);
   } else if (detectEntitiesDoc(doc)) {
     validateEntitiesDoc(doc, diagnosticCollection);
+  } else if (detectAuthDoc(doc)) {
+    validateAuthDoc(doc, diagnosticCollection);
+  } else if (detectTemplateLinksDoc(doc)) {
+    validateTemplateLinksDoc(doc, diagnosticCollection);
   }
 };
 
@@ -228,9 +281,11 @@ export const validateCedarDoc = async (
 
   const diagnostics: vscode.Diagnostic[] = [];
   reportFormatterOff(cedarDoc, diagnostics);
-  const syntaxResult: cedar.ValidateSyntaxResult = cedar.validateSyntax(
-    cedarDoc.getText()
-  );
+  // replaces the policies parsed from an earlier version of the document
+  const cedarUri = cedarDoc.uri.toString();
+  const syntaxResult: cedar.ValidateSyntaxResult =
+    cedarWorkspace.updatePolicies(cedarUri, cedarDoc.getText());
+  workspacePoliciesUris.add(cedarUri);
   let success = syntaxResult.success;
   if (syntaxResult.errors) {
     addSyntaxDiagnosticErrors(diagnostics, syntaxResult.errors, cedarDoc);
@@ -240,41 +295,32 @@ export const validateCedarDoc = async (
       if (validateSchemaDoc(schemaDoc, diagnosticCollection, userInitiated)) {
         validationCache.associateSchemaWithDoc(schemaDoc, cedarDoc);
 
-        parseCedarPoliciesDoc(cedarDoc, (policyRange, policyText) => {
-          let policyResult: cedar.ValidatePolicyResult;
-          if (schemaDoc.languageId === 'cedarschema') {
-            policyResult = cedar.validatePolicySchemaCedar(
-              schemaDoc.getText(),
-              policyText
-            );
-          } else {
-            policyResult = cedar.validatePolicySchemaJSON(
-              schemaDoc.getText(),
-              policyText
-            );
-          }
-          if (policyResult.warnings) {
-            addPolicyResultMessages(
-              diagnostics,
-              policyResult.warnings,
-              policyText,
-              policyRange.effectRange,
-              policyRange.range.start.line,
-              true
-            );
-          }
-          if (policyResult.success === false && policyResult.errors) {
-            addPolicyResultMessages(
-              diagnostics,
-              policyResult.errors,
-              policyText,
-              policyRange.effectRange,
-              policyRange.range.start.line,
-              false
-            );
-          }
-          policyResult.free();
-        });
+        const validationMode = vscode.workspace
+          .getConfiguration('cedar')
+          .get<string>('validationMode', 'strict');
+        const policyResult: cedar.ValidatePolicyResult =
+          cedarWorkspace.validatePolicies(
+            ensureWorkspaceSchema(schemaDoc),
+            cedarUri,
+            validationMode
+          );
+        if (policyResult.warnings) {
+          addPolicyResultMessages(
+            diagnostics,
+            policyResult.warnings,
+            cedarDoc,
+            true
+          );
+        }
+        if (policyResult.success === false && policyResult.errors) {
+          addPolicyResultMessages(
+            diagnostics,
+            policyResult.errors,
+            cedarDoc,
+            false
+          );
+        }
+        policyResult.free();
       }
     }
   }
@@ -300,20 +346,16 @@ export const validateSchemaDoc = (
   }
   // console.log(`validateSchemaDoc ${schemaDoc.uri.toString()}`);
 
-  let schemaResult: cedar.ValidateSchemaResult;
-  const schema = schemaDoc.getText();
-  if (schemaDoc.languageId === 'cedarschema') {
-    schemaResult = cedar.validateSchemaCedar(schema);
-  } else {
-    schemaResult = cedar.validateSchemaJSON(schema);
-  }
+  const schemaResult: cedar.ValidateSchemaResult =
+    updateWorkspaceSchema(schemaDoc);
   const success = schemaResult.success;
   if (schemaResult.success === false && schemaResult.errors) {
     let schemaDiagnostics: vscode.Diagnostic[] = [];
-    let vse = schemaResult.errors.map((e) => {
-      return { message: e.message, offset: e.offset, length: e.length };
-    });
-    addSyntaxDiagnosticErrors(schemaDiagnostics, vse, schemaDoc);
+    addSyntaxDiagnosticErrors(
+      schemaDiagnostics,
+      schemaResult.errors,
+      schemaDoc
+    );
     diagnosticCollection.set(schemaDoc.uri, schemaDiagnostics);
   } else {
     // reset any errors for the schema from a previous validateSchema
@@ -321,10 +363,7 @@ export const validateSchemaDoc = (
 
     if (schemaResult.warnings) {
       let schemaDiagnostics: vscode.Diagnostic[] = [];
-      schemaResult.warnings.map((w) => {
-        const range = determineRangeFromOffset(schemaDoc, w.offset, w.length);
-        addValidationDiagnosticWarning(schemaDiagnostics, w.message, range);
-      });
+      addValidationDiagnosticWarnings(schemaDiagnostics, schemaResult.warnings);
       diagnosticCollection.set(schemaDoc.uri, schemaDiagnostics);
     }
 
@@ -363,18 +402,11 @@ export const determineEntityTypes = (
   const types: string[] = [];
   const expr = scope === 'action' ? 'context.__vscode__' : scope;
   const tmpPolicy = `${head} when { ${expr} };`;
-  let policyResult: cedar.ValidatePolicyResult;
-  if (schemaDoc.languageId === 'cedarschema') {
-    policyResult = cedar.validatePolicySchemaCedar(
-      schemaDoc.getText(),
-      tmpPolicy
-    );
-  } else {
-    policyResult = cedar.validatePolicySchemaJSON(
-      schemaDoc.getText(),
+  const policyResult: cedar.ValidatePolicyResult =
+    cedarWorkspace.validatePolicyText(
+      ensureWorkspaceSchema(schemaDoc),
       tmpPolicy
     );
-  }
   if (policyResult.success === false && policyResult.errors) {
     policyResult.errors.forEach((e) => {
       let found =
@@ -416,18 +448,14 @@ export const validateEntitiesDoc = async (
     if (validateSchemaDoc(schemaDoc, diagnosticCollection, userInitiated)) {
       validationCache.associateSchemaWithDoc(schemaDoc, entitiesDoc);
 
-      let entitiesResult: cedar.ValidateEntitiesResult;
-      if (schemaDoc.languageId === 'cedarschema') {
-        entitiesResult = cedar.validateEntitiesSchemaCedar(
-          schemaDoc.getText(),
-          entities
-        );
-      } else {
-        entitiesResult = cedar.validateEntitiesSchemaJSON(
-          schemaDoc.getText(),
-          entities
+      const entitiesUri = entitiesDoc.uri.toString();
+      cedarWorkspace.updateEntities(entitiesUri, entities);
+      workspaceEntitiesUris.add(entitiesUri);
+      const entitiesResult: cedar.ValidateEntitiesResult =
+        cedarWorkspace.validateEntities(
+          ensureWorkspaceSchema(schemaDoc),
+          entitiesUri
         );
-      }
       success = entitiesResult.success;
       if (entitiesResult.success === false && entitiesResult.errors) {
         addSyntaxDiagnosticErrors(
@@ -452,3 +480,84 @@ export const validateEntitiesDoc = async (
 
   return Promise.resolve(success);
 };
+
+export const validateAuthDoc = async (
+  authDoc: vscode.TextDocument,
+  diagnosticCollection: vscode.DiagnosticCollection
+): Promise<boolean> => {
+  const cachedItem = validationCache.check(authDoc);
+  if (cachedItem) {
+    return Promise.resolve(cachedItem.valid);
+  }
+
+  let success = false;
+  let authDiagnostics: vscode.Diagnostic[] = [];
+
+  const schemaDoc = await getSchemaTextDocument(authDoc);
+  if (schemaDoc && validateSchemaDoc(schemaDoc, diagnosticCollection)) {
+    validationCache.associateSchemaWithDoc(schemaDoc, authDoc);
+
+    const requestResult: cedar.ValidateRequestResult =
+      schemaDoc.languageId === 'cedarschema'
+        ? cedar.validateRequestSchemaCedar(
+            schemaDoc.getText(),
+            authDoc.getText()
+          )
+        : cedar.validateRequestSchemaJSON(
+            schemaDoc.getText(),
+            authDoc.getText()
+          );
+    success = requestResult.success;
+    if (requestResult.errors) {
+      addPositionedDiagnosticErrors(authDiagnostics, requestResult.errors);
+    }
+    requestResult.free();
+  }
+
+  diagnosticCollection.set(authDoc.uri, authDiagnostics);
+
+  validationCache.store(authDoc, success);
+
+  return Promise.resolve(success);
+};
+
+export const validateTemplateLinksDoc = async (
+  linksDoc: vscode.TextDocument,
+  diagnosticCollection: vscode.DiagnosticCollection
+): Promise<boolean> => {
+  // not cached, since the templates can change without the links
+  let success = false;
+  let linksDiagnostics: vscode.Diagnostic[] = [];
+
+  const schemaDoc = await getSchemaTextDocument(linksDoc);
+  const templatesDoc = await getTemplatesTextDocument(linksDoc);
+  if (
+    schemaDoc &&
+    templatesDoc &&
+    validateSchemaDoc(schemaDoc, diagnosticCollection)
+  ) {
+    validationCache.associateSchemaWithDoc(schemaDoc, linksDoc);
+
+    const linksResult: cedar.ValidateTemplateLinksResult =
+      schemaDoc.languageId === 'cedarschema'
+        ? cedar.validateTemplateLinksSchemaCedar(
+            schemaDoc.getText(),
+            templatesDoc.getText(),
+            linksDoc.getText()
+          )
+        : cedar.validateTemplateLinksSchemaJSON(
+            schemaDoc.getText(),
+            templatesDoc.getText(),
+            linksDoc.getText()
+          );
+    success = linksResult.success;
+    if (linksResult.errors) {
+      addPositionedDiagnosticErrors(linksDiagnostics, linksResult.errors);
+    }
+    linksResult.free();
+  }
+
+  diagnosticCollection.set(linksDoc.uri, linksDiagnostics);
+
+  return Promise.resolve(success);
+};
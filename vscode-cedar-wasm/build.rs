@@ -20,8 +20,7 @@ fn main() {
     
     let cedar_pkg = packages.iter()
         .find(|p| p.get("name")
-            .and_then(|n| n.as_str()) 
-            .map_or(false, |n| n == "cedar-policy"))
+            .and_then(|n| n.as_str()) == Some("cedar-policy"))
         .expect("cedar-policy package not found");
     
     let version = cedar_pkg.get("version")
@@ -0,0 +1,436 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use cedar_policy::{
+    Authorizer, Context, Entities, EntityTypeName, EntityUid, PolicySet, Request, Schema,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+use crate::authorizer::{decision_name, field_message, policy_name};
+use crate::entities_validator::parse_entities_messages;
+use crate::json_spans::{serde_error_offset, JsonNode};
+use crate::syntax_validator::parse_policies;
+use crate::utils::to_js_value;
+use crate::validate_message::{convert_messages_to_js_array, ValidateMessage};
+
+#[wasm_bindgen(typescript_custom_section)]
+const ACCESS_MATRIX_RESULT: &'static str = r#"
+export class AccessMatrixEntry {
+  readonly principal: string;
+  readonly action: string;
+  readonly resource: string;
+  readonly decision: "allow" | "deny" | undefined;
+  readonly determiningPolicies: Array<string>;
+  readonly errors: Array<string>;
+}
+export class AccessMatrixResult {
+  free(): void;
+  readonly success: boolean;
+  readonly entries: Array<AccessMatrixEntry> | undefined;
+  readonly errors: Array<ValidateMessage> | undefined;
+}"#;
+
+#[wasm_bindgen(getter_with_clone, skip_typescript)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessMatrixResult {
+    #[wasm_bindgen(readonly)]
+    pub success: bool,
+    entries: Option<Vec<AccessMatrixEntry>>,
+    errors: Option<Vec<ValidateMessage>>,
+}
+
+#[wasm_bindgen]
+impl AccessMatrixResult {
+    #[wasm_bindgen(getter)]
+    pub fn entries(&self) -> JsValue {
+        self.entries
+            .as_ref()
+            .map_or(JsValue::UNDEFINED, to_js_value)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Option<js_sys::Array> {
+        self.errors.as_deref().map(convert_messages_to_js_array)
+    }
+}
+
+/// The response to one principal, action and resource of the matrix, where
+/// `errors` are request validation or evaluation errors
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessMatrixEntry {
+    pub principal: String,
+    pub action: String,
+    pub resource: String,
+    pub decision: Option<String>,
+    pub determining_policies: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// Entity types or UIDs selecting the principals, actions and resources of
+/// the matrix, where omitted actions select every action in the schema and
+/// `context` is used for every request
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AccessMatrixSelectors {
+    principals: Vec<String>,
+    actions: Option<Vec<String>>,
+    resources: Vec<String>,
+    #[serde(default = "empty_context")]
+    context: serde_json::Value,
+}
+
+fn empty_context() -> serde_json::Value {
+    serde_json::Value::Object(serde_json::Map::new())
+}
+
+fn create_error_result(errors: Vec<ValidateMessage>) -> AccessMatrixResult {
+    AccessMatrixResult {
+        success: false,
+        entries: None,
+        errors: Some(errors),
+    }
+}
+
+/// Most principal, action and resource combinations a matrix authorizes
+const MAX_ENTRIES: usize = 10_000;
+
+/// Resolve each selector in the `field` array of the selectors document to
+/// the UID it names, or to every UID in `candidates` of the type it names
+fn select(
+    src: &str,
+    root: Option<&JsonNode>,
+    field: &str,
+    selectors: &[String],
+    candidates: &[EntityUid],
+    errors: &mut Vec<ValidateMessage>,
+) -> Vec<EntityUid> {
+    let mut selected = BTreeSet::new();
+    for (i, selector) in selectors.iter().enumerate() {
+        if let Ok(uid) = EntityUid::from_str(selector) {
+            selected.insert(uid);
+            continue;
+        }
+        match EntityTypeName::from_str(selector) {
+            Ok(type_name) => selected.extend(
+                candidates
+                    .iter()
+                    .filter(|uid| uid.type_name() == &type_name)
+                    .cloned(),
+            ),
+            Err(e) => {
+                let e = format!("`{selector}` is neither an entity UID nor an entity type: {e}");
+                errors.push(selector_message(src, root, field, i, &e));
+            }
+        }
+    }
+    selected.into_iter().collect()
+}
+
+/// `e` positioned on the selector at `index` of the `field` array
+fn selector_message(
+    src: &str,
+    root: Option<&JsonNode>,
+    field: &str,
+    index: usize,
+    e: &dyn std::fmt::Display,
+) -> ValidateMessage {
+    match root
+        .and_then(|root| root.get(field))
+        .and_then(|node| node.elements().get(index))
+    {
+        Some(node) => ValidateMessage::new(e.to_string(), src, node.offset, node.length),
+        None => field_message(src, root, field, e),
+    }
+}
+
+fn authorize_entry(
+    schema: &Schema,
+    pset: &PolicySet,
+    entities: &Entities,
+    (principal, action, resource): (&EntityUid, &EntityUid, &EntityUid),
+    context: &serde_json::Value,
+) -> AccessMatrixEntry {
+    let mut entry = AccessMatrixEntry {
+        principal: principal.to_string(),
+        action: action.to_string(),
+        resource: resource.to_string(),
+        decision: None,
+        determining_policies: Vec::new(),
+        errors: Vec::new(),
+    };
+    let context = match Context::from_json_value(context.clone(), Some((schema, action))) {
+        Ok(context) => context,
+        Err(e) => {
+            entry.errors.push(e.to_string());
+            return entry;
+        }
+    };
+    let request = match Request::new(
+        principal.clone(),
+        action.clone(),
+        resource.clone(),
+        context,
+        Some(schema),
+    ) {
+        Ok(request) => request,
+        Err(e) => {
+            entry.errors.push(e.to_string());
+            return entry;
+        }
+    };
+    let response = Authorizer::new().is_authorized(&request, pset, entities);
+    entry.decision = Some(decision_name(response.decision()).to_string());
+    entry.determining_policies = response
+        .diagnostics()
+        .reason()
+        .map(|id| policy_name(pset, id))
+        .collect();
+    entry.errors = response
+        .diagnostics()
+        .errors()
+        .map(|e| e.to_string())
+        .collect();
+    entry
+}
+
+/// Authorize every combination of the selected principals, actions and
+/// resources that the `appliesTo` of the action in `schema` permits
+fn authorize_matrix(
+    schema: &Schema,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_selectors_str: &str,
+) -> AccessMatrixResult {
+    let pset = match parse_policies(input_policies_str) {
+        Ok(pset) => pset,
+        Err(errors) => return create_error_result(errors),
+    };
+    let entities = match parse_entities_messages(input_entities_str, Some(schema)) {
+        Ok(entities) => entities,
+        Err(errors) => return create_error_result(errors),
+    };
+    let selectors: AccessMatrixSelectors = match serde_json::from_str(input_selectors_str) {
+        Ok(selectors) => selectors,
+        Err(e) => {
+            return create_error_result(vec![ValidateMessage::new(
+                format!("invalid selectors: {e}"),
+                input_selectors_str,
+                serde_error_offset(input_selectors_str, &e),
+                0,
+            )])
+        }
+    };
+
+    let root = JsonNode::parse(input_selectors_str);
+    let src = input_selectors_str;
+    let mut errors = Vec::new();
+    let uids: Vec<EntityUid> = entities.iter().map(|entity| entity.uid()).collect();
+    let mut declared: Vec<EntityUid> = schema.actions().cloned().collect();
+    declared.sort();
+    let principals = select(
+        src,
+        root.as_ref(),
+        "principals",
+        &selectors.principals,
+        &uids,
+        &mut errors,
+    );
+    let resources = select(
+        src,
+        root.as_ref(),
+        "resources",
+        &selectors.resources,
+        &uids,
+        &mut errors,
+    );
+    let actions = match &selectors.actions {
+        Some(actions) => select(
+            src,
+            root.as_ref(),
+            "actions",
+            actions,
+            &declared,
+            &mut errors,
+        ),
+        None => declared.clone(),
+    };
+    for (i, action) in selectors.actions.iter().flatten().enumerate() {
+        if let Ok(uid) = EntityUid::from_str(action) {
+            if !declared.contains(&uid) {
+                let e = format!("action `{uid}` is not declared in the schema");
+                errors.push(selector_message(src, root.as_ref(), "actions", i, &e));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        return create_error_result(errors);
+    }
+
+    let mut tuples = Vec::new();
+    for action in &actions {
+        let (Some(principal_types), Some(resource_types)) = (
+            schema.principals_for_action(action),
+            schema.resources_for_action(action),
+        ) else {
+            continue;
+        };
+        let principal_types: HashSet<&EntityTypeName> = principal_types.collect();
+        let resource_types: HashSet<&EntityTypeName> = resource_types.collect();
+        for principal in principals
+            .iter()
+            .filter(|uid| principal_types.contains(uid.type_name()))
+        {
+            for resource in resources
+                .iter()
+                .filter(|uid| resource_types.contains(uid.type_name()))
+            {
+                tuples.push((principal, action, resource));
+            }
+        }
+    }
+    if tuples.len() > MAX_ENTRIES {
+        return create_error_result(vec![ValidateMessage::without_span(format!(
+            "the selectors choose {} principal, action and resource combinations, more than the {MAX_ENTRIES} allowed",
+            tuples.len()
+        ))]);
+    }
+
+    let entries = tuples
+        .into_iter()
+        .map(|tuple| authorize_entry(schema, &pset, &entities, tuple, &selectors.context))
+        .collect();
+    AccessMatrixResult {
+        success: true,
+        entries: Some(entries),
+        errors: None,
+    }
+}
+
+#[wasm_bindgen(js_name = authorizeMatrixSchemaJSON)]
+pub fn authorize_matrix_schema_json(
+    input_schema_str: &str,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_selectors_str: &str,
+) -> AccessMatrixResult {
+    match Schema::from_json_str(input_schema_str) {
+        Ok(schema) => authorize_matrix(
+            &schema,
+            input_policies_str,
+            input_entities_str,
+            input_selectors_str,
+        ),
+        Err(e) => create_error_result(vec![ValidateMessage::without_span(e.to_string())]),
+    }
+}
+
+#[wasm_bindgen(js_name = authorizeMatrixSchemaCedar)]
+pub fn authorize_matrix_schema_cedar(
+    input_schema_str: &str,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_selectors_str: &str,
+) -> AccessMatrixResult {
+    match Schema::from_cedarschema_str(input_schema_str) {
+        Ok((schema, _warnings)) => authorize_matrix(
+            &schema,
+            input_policies_str,
+            input_entities_str,
+            input_selectors_str,
+        ),
+        Err(e) => create_error_result(vec![ValidateMessage::without_span(e.to_string())]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    fn read_testdata(file: &str) -> String {
+        fs::read_to_string(format!("../testdata/policytests/{file}"))
+            .expect("Failed to read testdata")
+    }
+
+    fn authorize(selectors: &str) -> AccessMatrixResult {
+        authorize_matrix_schema_cedar(
+            &read_testdata("cedarschema"),
+            &read_testdata("policies.cedar"),
+            &read_testdata("cedarentities.json"),
+            selectors,
+        )
+    }
+
+    #[test]
+    fn authorize_matrix_enumerates_applicable_tuples() {
+        let result = authorize(r#"{ "principals": ["User"], "resources": ["Document", "Group"] }"#);
+        assert!(result.success, "{:?}", result.errors);
+        let entries = result.entries.unwrap();
+        let matrix: Vec<(&str, &str, &str)> = entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.principal.as_str(),
+                    entry.action.as_str(),
+                    entry.decision.as_deref().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            matrix,
+            vec![
+                (r#"User::"alice""#, r#"Action::"edit""#, "deny"),
+                (r#"User::"bob""#, r#"Action::"edit""#, "allow"),
+                (r#"User::"alice""#, r#"Action::"view""#, "allow"),
+                (r#"User::"bob""#, r#"Action::"view""#, "allow"),
+            ]
+        );
+        assert!(entries
+            .iter()
+            .all(|entry| entry.resource == r#"Document::"report""#));
+        assert_eq!(entries[1].determining_policies, vec![String::from("owner")]);
+    }
+
+    #[test]
+    fn authorize_matrix_positions_selector_errors() {
+        let selectors = r#"{
+  "principals": ["User::\"alice\""],
+  "actions": ["Action::\"view\"", "Action::\"delete\""],
+  "resources": ["Document::\"draft\"", "not a type"]
+}"#;
+        let errors = authorize(selectors).errors.unwrap();
+        let lines: Vec<usize> = errors.iter().map(|e| e.start_line).collect();
+        assert_eq!(lines, vec![3, 2]);
+        assert_eq!(errors[1].start_character, 34);
+
+        let result = authorize(
+            r#"{ "principals": ["User::\"alice\""], "actions": ["Action::\"view\""], "resources": ["Document::\"draft\""] }"#,
+        );
+        assert_eq!(
+            result.entries.unwrap()[0].decision,
+            Some(String::from("allow"))
+        );
+    }
+
+    #[test]
+    fn authorize_matrix_limits_the_number_of_entries() {
+        let uids = |ty: &str| {
+            (0..100)
+                .map(|i| format!("\"{ty}::\\\"{i}\\\"\""))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let selectors = format!(
+            r#"{{ "principals": [{}], "resources": [{}] }}"#,
+            uids("User"),
+            uids("Document")
+        );
+        let result = authorize(&selectors);
+        assert!(!result.success);
+        assert!(result.errors.unwrap()[0].message.contains("20000"));
+    }
+}
@@ -0,0 +1,482 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use cedar_policy::{
+    Authorizer, Context, ContextJsonError, Decision, EntityUid, PolicyId, PolicySet, Request,
+    RequestValidationError, Schema,
+};
+use miette::Diagnostic;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+use crate::entities_validator::parse_entities_messages;
+use crate::json_spans::{serde_error_offset, JsonNode};
+use crate::validate_message::{convert_messages_to_js_array, ValidateMessage};
+
+#[wasm_bindgen(typescript_custom_section)]
+const IS_AUTHORIZED_RESULT: &'static str = r#"
+export class IsAuthorizedResult {
+  free(): void;
+  readonly success: boolean;
+  readonly decision: "allow" | "deny" | undefined;
+  readonly determiningPolicies: Array<string> | undefined;
+  readonly errors: Array<ValidateMessage> | undefined;
+}
+export class ValidateRequestResult {
+  free(): void;
+  readonly success: boolean;
+  readonly errors: Array<ValidateMessage> | undefined;
+}"#;
+
+#[wasm_bindgen(getter_with_clone, skip_typescript)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IsAuthorizedResult {
+    #[wasm_bindgen(readonly)]
+    pub success: bool,
+    #[wasm_bindgen(readonly)]
+    pub decision: Option<String>,
+    determining_policies: Option<Vec<String>>,
+    errors: Option<Vec<ValidateMessage>>,
+}
+
+#[wasm_bindgen]
+impl IsAuthorizedResult {
+    #[wasm_bindgen(getter, js_name = determiningPolicies)]
+    pub fn determining_policies(&self) -> Option<js_sys::Array> {
+        self.determining_policies
+            .as_ref()
+            .map(|ids| ids.iter().map(|id| JsValue::from_str(id)).collect())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Option<js_sys::Array> {
+        self.errors.as_deref().map(convert_messages_to_js_array)
+    }
+}
+
+#[wasm_bindgen(getter_with_clone, skip_typescript)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValidateRequestResult {
+    #[wasm_bindgen(readonly)]
+    pub success: bool,
+    errors: Option<Vec<ValidateMessage>>,
+}
+
+#[wasm_bindgen]
+impl ValidateRequestResult {
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Option<js_sys::Array> {
+        self.errors.as_deref().map(convert_messages_to_js_array)
+    }
+}
+
+/// Authorization request in the `cedarauth.json` format used by the Cedar CLI
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct AuthorizationRequest {
+    pub principal: String,
+    pub action: String,
+    pub resource: String,
+    #[serde(default = "empty_context")]
+    pub context: serde_json::Value,
+    /// JSON schema that editors use to check and complete the file
+    #[serde(rename = "$schema")]
+    _schema: Option<String>,
+}
+
+fn empty_context() -> serde_json::Value {
+    serde_json::Value::Object(serde_json::Map::new())
+}
+
+fn message(e: impl std::fmt::Display) -> ValidateMessage {
+    ValidateMessage::without_span(e.to_string())
+}
+
+fn create_error_result(e: impl std::fmt::Display) -> IsAuthorizedResult {
+    IsAuthorizedResult {
+        success: false,
+        decision: None,
+        determining_policies: None,
+        errors: Some(vec![message(e)]),
+    }
+}
+
+/// `e` positioned on `field` of the JSON object `root` parsed from `src`, or
+/// without a span when the field is missing
+pub(crate) fn field_message(
+    src: &str,
+    root: Option<&JsonNode>,
+    field: &str,
+    e: &dyn std::fmt::Display,
+) -> ValidateMessage {
+    match root.and_then(|root| root.get(field)) {
+        Some(node) => ValidateMessage::new(e.to_string(), src, node.offset, node.length),
+        None => ValidateMessage::without_span(e.to_string()),
+    }
+}
+
+pub(crate) fn parse_entity_uid(field: &str, uid_str: &str) -> Result<EntityUid, String> {
+    EntityUid::from_str(uid_str).map_err(|e| format!("invalid `{field}` entity `{uid_str}`: {e}"))
+}
+
+/// The `cedarauth.json` field a request validation error is about
+pub(crate) fn request_error_field(e: &RequestValidationError) -> &'static str {
+    match e {
+        RequestValidationError::UndeclaredAction(_) => "action",
+        RequestValidationError::UndeclaredPrincipalType(_)
+        | RequestValidationError::InvalidPrincipalType(_) => "principal",
+        RequestValidationError::UndeclaredResourceType(_)
+        | RequestValidationError::InvalidResourceType(_) => "resource",
+        _ => "context",
+    }
+}
+
+/// Build a Cedar `Request` from the text of a `cedarauth.json` document,
+/// validating it against `schema` when one is provided, with each error
+/// positioned on the JSON field it is about
+pub(crate) fn parse_request(
+    input_request_str: &str,
+    schema: Option<&Schema>,
+) -> Result<Request, Vec<ValidateMessage>> {
+    let request: AuthorizationRequest = serde_json::from_str(input_request_str).map_err(|e| {
+        vec![ValidateMessage::new(
+            format!("invalid authorization request: {e}"),
+            input_request_str,
+            serde_error_offset(input_request_str, &e),
+            0,
+        )]
+    })?;
+    let root = JsonNode::parse(input_request_str);
+    let at_field = |field: &str, e: &dyn std::fmt::Display| {
+        field_message(input_request_str, root.as_ref(), field, e)
+    };
+
+    let mut errors = Vec::new();
+    let mut entity_uid = |field: &str, uid_str: &str| match parse_entity_uid(field, uid_str) {
+        Ok(uid) => Some(uid),
+        Err(e) => {
+            errors.push(at_field(field, &e));
+            None
+        }
+    };
+    let principal = entity_uid("principal", &request.principal);
+    let action = entity_uid("action", &request.action);
+    let resource = entity_uid("resource", &request.resource);
+    let (Some(principal), Some(action), Some(resource)) = (principal, action, resource) else {
+        return Err(errors);
+    };
+    match Context::from_json_value(request.context, schema.map(|s| (s, &action))) {
+        Ok(context) => Request::new(principal, action, resource, context, schema)
+            .map_err(|e| vec![at_field(request_error_field(&e), &e)]),
+        Err(ContextJsonError::MissingAction(e)) => Err(vec![at_field("action", &e)]),
+        Err(e) => {
+            // still check the principal and resource types against the action
+            let mut errors = vec![at_field("context", &e)];
+            if let Err(e) = Request::new(principal, action, resource, Context::empty(), schema) {
+                if request_error_field(&e) != "context" {
+                    errors.insert(0, at_field(request_error_field(&e), &e));
+                }
+            }
+            Err(errors)
+        }
+    }
+}
+
+pub(crate) fn decision_name(decision: Decision) -> &'static str {
+    match decision {
+        Decision::Allow => "allow",
+        Decision::Deny => "deny",
+    }
+}
+
+/// The `@id` annotation of a policy, or its generated ID when it has none,
+/// which is how outlines and validation diagnostics name policies too
+pub(crate) fn policy_name(pset: &PolicySet, id: &PolicyId) -> String {
+    pset.policy(id)
+        .and_then(|policy| policy.annotation("id"))
+        .map_or_else(|| id.to_string(), str::to_string)
+}
+
+fn is_authorized(
+    schema: Option<&Schema>,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_request_str: &str,
+) -> IsAuthorizedResult {
+    let pset = match PolicySet::from_str(input_policies_str) {
+        Ok(pset) => pset,
+        Err(e) => return create_error_result(e),
+    };
+    let entities = match parse_entities_messages(input_entities_str, schema) {
+        Ok(entities) => entities,
+        Err(errors) => {
+            return IsAuthorizedResult {
+                success: false,
+                decision: None,
+                determining_policies: None,
+                errors: Some(errors),
+            }
+        }
+    };
+    let request = match parse_request(input_request_str, schema) {
+        Ok(request) => request,
+        Err(errors) => {
+            return IsAuthorizedResult {
+                success: false,
+                decision: None,
+                determining_policies: None,
+                errors: Some(errors),
+            }
+        }
+    };
+
+    let response = Authorizer::new().is_authorized(&request, &pset, &entities);
+    let determining_policies = response
+        .diagnostics()
+        .reason()
+        .map(|id| policy_name(&pset, id))
+        .collect();
+    let mut evaluation_errs = Vec::new();
+    response.diagnostics().errors().for_each(|e| {
+        match e.labels().and_then(|mut labels| labels.next()) {
+            Some(labeled_span) => evaluation_errs.push(ValidateMessage::new(
+                e.to_string(),
+                input_policies_str,
+                labeled_span.offset(),
+                labeled_span.len(),
+            )),
+            None => evaluation_errs.push(message(e)),
+        }
+    });
+
+    IsAuthorizedResult {
+        success: true,
+        decision: Some(decision_name(response.decision()).to_string()),
+        determining_policies: Some(determining_policies),
+        errors: if evaluation_errs.is_empty() {
+            None
+        } else {
+            Some(evaluation_errs)
+        },
+    }
+}
+
+#[wasm_bindgen(js_name = isAuthorized)]
+pub fn is_authorized_no_schema(
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_request_str: &str,
+) -> IsAuthorizedResult {
+    is_authorized(
+        None,
+        input_policies_str,
+        input_entities_str,
+        input_request_str,
+    )
+}
+
+#[wasm_bindgen(js_name = isAuthorizedSchemaJSON)]
+pub fn is_authorized_schema_json(
+    input_schema_str: &str,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_request_str: &str,
+) -> IsAuthorizedResult {
+    let schema = match Schema::from_json_str(input_schema_str) {
+        Ok(schema) => schema,
+        Err(e) => return create_error_result(e),
+    };
+    is_authorized(
+        Some(&schema),
+        input_policies_str,
+        input_entities_str,
+        input_request_str,
+    )
+}
+
+#[wasm_bindgen(js_name = isAuthorizedSchemaCedar)]
+pub fn is_authorized_schema_cedar(
+    input_schema_str: &str,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_request_str: &str,
+) -> IsAuthorizedResult {
+    let schema = match Schema::from_cedarschema_str(input_schema_str) {
+        Ok((schema, _warnings)) => schema,
+        Err(e) => return create_error_result(e),
+    };
+    is_authorized(
+        Some(&schema),
+        input_policies_str,
+        input_entities_str,
+        input_request_str,
+    )
+}
+
+fn validate_request(schema: &Schema, input_request_str: &str) -> ValidateRequestResult {
+    match parse_request(input_request_str, Some(schema)) {
+        Ok(_) => ValidateRequestResult {
+            success: true,
+            errors: None,
+        },
+        Err(errors) => ValidateRequestResult {
+            success: false,
+            errors: Some(errors),
+        },
+    }
+}
+
+fn validate_request_error_result(e: impl std::fmt::Display) -> ValidateRequestResult {
+    ValidateRequestResult {
+        success: false,
+        errors: Some(vec![message(e)]),
+    }
+}
+
+/// Check that the principal and resource of a `cedarauth.json` request are
+/// valid for its action and that its context matches the action's context
+/// type
+#[wasm_bindgen(js_name = validateRequestSchemaJSON)]
+pub fn validate_request_schema_json(
+    input_schema_str: &str,
+    input_request_str: &str,
+) -> ValidateRequestResult {
+    match Schema::from_json_str(input_schema_str) {
+        Ok(schema) => validate_request(&schema, input_request_str),
+        Err(e) => validate_request_error_result(e),
+    }
+}
+
+#[wasm_bindgen(js_name = validateRequestSchemaCedar)]
+pub fn validate_request_schema_cedar(
+    input_schema_str: &str,
+    input_request_str: &str,
+) -> ValidateRequestResult {
+    match Schema::from_cedarschema_str(input_schema_str) {
+        Ok((schema, _warnings)) => validate_request(&schema, input_request_str),
+        Err(e) => validate_request_error_result(e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    const ENTITIES: &str = r#"[
+        { "uid": { "type": "User", "id": "alice" }, "attrs": { "jobLevel": 7 }, "parents": [], "tags": { "write": ["docs"] } },
+        { "uid": { "type": "User", "id": "bob" }, "attrs": { "jobLevel": 3 }, "parents": [] },
+        { "uid": { "type": "Document", "id": "doc" }, "attrs": { "owner": { "__entity": { "type": "User", "id": "bob" } } }, "parents": [], "tags": { "write": ["docs"] } }
+    ]"#;
+
+    fn request(principal: &str) -> String {
+        format!(
+            r#"{{ "principal": "User::\"{principal}\"", "action": "Action::\"writeDoc\"", "resource": "Document::\"doc\"", "context": {{}} }}"#
+        )
+    }
+
+    fn read_testdata(file: &str) -> String {
+        fs::read_to_string(format!("../testdata/RFC82/{file}")).expect("Failed to read testdata")
+    }
+
+    #[test]
+    fn is_authorized_allows_owner() {
+        let result = is_authorized_schema_cedar(
+            &read_testdata("cedarschema"),
+            &read_testdata("policies.cedar"),
+            ENTITIES,
+            &request("bob"),
+        );
+        assert!(result.success);
+        assert_eq!(result.decision, Some(String::from("allow")));
+        assert_eq!(
+            result.determining_policies,
+            Some(vec![String::from("policy0")])
+        );
+    }
+
+    #[test]
+    fn is_authorized_denies_by_default() {
+        let result = is_authorized_no_schema(
+            "permit(principal == User::\"alice\", action, resource);",
+            "[]",
+            &request("bob"),
+        );
+        assert!(result.success);
+        assert_eq!(result.decision, Some(String::from("deny")));
+        assert_eq!(result.determining_policies, Some(vec![]));
+
+        let result = is_authorized_no_schema(
+            "@id(\"bob\")\npermit(principal == User::\"bob\", action, resource);",
+            "[]",
+            &request("bob"),
+        );
+        assert_eq!(result.decision, Some(String::from("allow")));
+        assert_eq!(result.determining_policies, Some(vec![String::from("bob")]));
+    }
+
+    #[test]
+    fn is_authorized_accepts_json_schema_reference() {
+        let result = is_authorized_no_schema(
+            "permit(principal, action, resource);",
+            "[]",
+            r#"{ "$schema": "https://example.com/cedarauth.schema.json", "principal": "User::\"bob\"", "action": "Action::\"writeDoc\"", "resource": "Document::\"doc\"" }"#,
+        );
+        assert!(result.success, "{:?}", result.errors);
+        assert_eq!(result.decision, Some(String::from("allow")));
+    }
+
+    #[test]
+    fn is_authorized_reports_evaluation_errors() {
+        let result = is_authorized_no_schema(
+            "permit(principal, action, resource) when { principal.missing };",
+            "[]",
+            &request("alice"),
+        );
+        assert!(result.success);
+        assert_eq!(result.decision, Some(String::from("deny")));
+        assert!(result.errors.is_some());
+    }
+
+    #[test]
+    fn is_authorized_rejects_request_not_in_schema() {
+        let result = is_authorized_schema_json(
+            &read_testdata("cedarschema.json"),
+            &read_testdata("policies.cedar"),
+            ENTITIES,
+            r#"{ "principal": "Document::\"doc\"", "action": "Action::\"writeDoc\"", "resource": "Document::\"doc\"", "context": {} }"#,
+        );
+        assert!(!result.success);
+        assert!(result.errors.is_some());
+    }
+
+    #[test]
+    fn validate_request_positions_errors_on_fields() {
+        let schema = read_testdata("cedarschema");
+        let invalid = r#"{
+  "principal": "Document::\"doc\"",
+  "action": "Action::\"writeDoc\"",
+  "resource": "Document::\"doc\"",
+  "context": { "unexpected": true }
+}"#;
+        let errors = validate_request_schema_cedar(&schema, invalid)
+            .errors
+            .unwrap();
+        let lines: Vec<usize> = errors.iter().map(|e| e.start_line).collect();
+        assert_eq!(lines, vec![1, 4]);
+
+        let undeclared = r#"{ "principal": "User::\"bob\"", "action": "Action::\"fly\"", "resource": "Document::\"doc\"" }"#;
+        let errors = validate_request_schema_cedar(&schema, undeclared)
+            .errors
+            .unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].offset, undeclared.find("\"Action").unwrap());
+
+        let errors = validate_request_schema_cedar(&schema, "{ \"principal\": 1 }")
+            .errors
+            .unwrap();
+        assert_eq!(errors[0].start_character, 15);
+        assert!(validate_request_schema_cedar(&schema, &request("bob")).success);
+    }
+}
@@ -0,0 +1,6 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+fn main() -> std::process::ExitCode {
+    vscode_cedar_wasm::lsp::main()
+}
@@ -0,0 +1,62 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// The extension's file associations, for the native binaries that find
+// Cedar files on disk themselves
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum FileKind {
+    Policies,
+    Schema,
+    SchemaJson,
+    Entities,
+    TemplateLinks,
+    Request,
+}
+
+/// The kind of Cedar file `name` is, following the extension's file
+/// associations
+pub(crate) fn file_kind(name: &str) -> Option<FileKind> {
+    let named = |file: &str| name == file || name.ends_with(&format!(".{file}"));
+    if name.ends_with(".cedar") {
+        Some(FileKind::Policies)
+    } else if named("cedarschema") {
+        Some(FileKind::Schema)
+    } else if named("cedarschema.json") {
+        Some(FileKind::SchemaJson)
+    } else if named("cedarentities.json") {
+        Some(FileKind::Entities)
+    } else if named("cedartemplatelinks.json") || named("cedarlinks.json") {
+        Some(FileKind::TemplateLinks)
+    } else if named("cedarauth.json") || named("cedarparc.json") {
+        Some(FileKind::Request)
+    } else {
+        None
+    }
+}
+
+/// The schema file in `files`, preferring a Cedar schema over a JSON schema
+/// translated from it, or `None` when there is not exactly one
+pub(crate) fn schema_file(files: &[(PathBuf, FileKind)]) -> Option<&Path> {
+    let cedar: BTreeSet<&Path> = files
+        .iter()
+        .filter(|(_, kind)| *kind == FileKind::Schema)
+        .map(|(path, _)| path.as_path())
+        .collect();
+    let schemas: Vec<&Path> = files
+        .iter()
+        .filter(|(path, kind)| match kind {
+            FileKind::Schema => true,
+            FileKind::SchemaJson => !cedar.contains(path.with_extension("").as_path()),
+            _ => false,
+        })
+        .map(|(path, _)| path.as_path())
+        .collect();
+    match schemas.as_slice() {
+        [schema] => Some(schema),
+        _ => None,
+    }
+}
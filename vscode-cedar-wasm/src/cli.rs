@@ -0,0 +1,586 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Native entry point for the `cedar-lint` binary, which runs the validators
+// the extension uses over a directory so CI reports the same diagnostics the
+// editor shows
+
+use cedar_policy::{Schema, ValidationMode, Validator};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::authorizer::parse_request;
+use crate::cedar_files::{file_kind, schema_file, FileKind};
+use crate::entities_validator::parse_entities;
+use crate::policy_validator::{parse_validation_mode, validate_parsed_policies};
+use crate::schema_validator::{parse_schema_cedar, parse_schema_json, ValidateSchemaResult};
+use crate::syntax_validator::parse_policies;
+use crate::template_links::validate_template_links;
+use crate::validate_message::ValidateMessage;
+
+const USAGE: &str = "\
+Usage: cedar-lint [OPTIONS] [DIR]
+
+Validate the Cedar files in DIR (default `.`): policies (*.cedar), schemas
+(cedarschema, *.cedarschema, cedarschema.json, *.cedarschema.json), entities
+(*.cedarentities.json), template links (*.cedartemplatelinks.json) and
+authorization requests (*.cedarauth.json). Template links are linked against
+the one policies file in their directory.
+
+Options:
+  --format <human|json|sarif>        Output format [default: human]
+  --schema <FILE>                    Schema to validate every file against,
+                                     instead of finding one per directory
+  --mode <strict|permissive|partial> Policy validation mode [default: strict]
+  -h, --help                         Print this help";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Human,
+    Json,
+    Sarif,
+}
+
+#[derive(Debug)]
+struct Options {
+    format: Format,
+    schema: Option<PathBuf>,
+    mode: ValidationMode,
+    root: PathBuf,
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut options = Options {
+        format: Format::Human,
+        schema: None,
+        mode: ValidationMode::Strict,
+        root: PathBuf::from("."),
+    };
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or(format!("missing value for `{name}`"));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--format" => {
+                options.format = match value("--format")?.as_str() {
+                    "human" => Format::Human,
+                    "json" => Format::Json,
+                    "sarif" => Format::Sarif,
+                    other => return Err(format!("unknown format `{other}`")),
+                }
+            }
+            "--schema" => options.schema = Some(PathBuf::from(value("--schema")?)),
+            "--mode" => options.mode = parse_validation_mode(Some(value("--mode")?))?,
+            other if other.starts_with('-') => return Err(format!("unknown option `{other}`")),
+            other => options.root = PathBuf::from(other),
+        }
+    }
+    Ok(Some(options))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A diagnostic for one file, with one-based lines and columns counted in
+/// UTF-16 code units as in the editor
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    pub path: String,
+    pub severity: Severity,
+    pub rule: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Finding {
+    fn new(path: &str, severity: Severity, rule: &str, message: &ValidateMessage) -> Self {
+        Finding {
+            path: path.to_string(),
+            severity,
+            rule: rule.to_string(),
+            message: message.message.clone(),
+            help: None,
+            line: message.start_line + 1,
+            column: message.start_character + 1,
+            end_line: message.end_line + 1,
+            end_column: message.end_character + 1,
+        }
+    }
+}
+
+/// Cedar files under `root` by directory, skipping hidden directories, build
+/// output and symbolic links to directories, which could lead back to a
+/// directory already searched
+fn find_files(root: &Path) -> io::Result<BTreeMap<PathBuf, Vec<(PathBuf, FileKind)>>> {
+    let mut dirs = BTreeMap::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries: Vec<_> = fs::read_dir(&dir)?
+            .map(|entry| entry.and_then(|entry| Ok((entry.path(), entry.file_type()?))))
+            .collect::<io::Result<_>>()?;
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut files = Vec::new();
+        for (path, file_type) in entries {
+            let name = path
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+            if file_type.is_dir() {
+                if !name.starts_with('.') && name != "node_modules" && name != "target" {
+                    pending.push(path);
+                }
+            } else if path.is_dir() {
+                continue;
+            } else if let Some(kind) = file_kind(&name) {
+                files.push((path, kind));
+            }
+        }
+        if !files.is_empty() {
+            dirs.insert(dir, files);
+        }
+    }
+    Ok(dirs)
+}
+
+struct Linter<'a> {
+    root: &'a Path,
+    mode: ValidationMode,
+    findings: Vec<Finding>,
+    /// Parsed schemas by path, `None` when the schema has errors
+    schemas: BTreeMap<PathBuf, Option<Schema>>,
+}
+
+impl Linter<'_> {
+    fn display_path(&self, path: &Path) -> String {
+        path.strip_prefix(self.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    }
+
+    fn add(&mut self, path: &Path, severity: Severity, rule: &str, messages: &[ValidateMessage]) {
+        let path = self.display_path(path);
+        self.findings.extend(
+            messages
+                .iter()
+                .map(|message| Finding::new(&path, severity, rule, message)),
+        );
+    }
+
+    fn read(&mut self, path: &Path) -> Option<String> {
+        match fs::read_to_string(path) {
+            Ok(text) => Some(text),
+            Err(e) => {
+                let message = ValidateMessage::without_span(format!("cannot read file: {e}"));
+                self.add(path, Severity::Error, "io", &[message]);
+                None
+            }
+        }
+    }
+
+    /// Parse the schema at `path` once, reporting its errors and warnings
+    fn schema(&mut self, path: &Path) -> Option<Schema> {
+        if let Some(schema) = self.schemas.get(path) {
+            return schema.clone();
+        }
+        let schema = self.read(path).and_then(|text| {
+            let (result, schema) = if path.extension().is_some_and(|ext| ext == "json") {
+                parse_schema_json(&text)
+            } else {
+                parse_schema_cedar(&text)
+            };
+            self.add_schema_result(path, result);
+            schema
+        });
+        self.schemas.insert(path.to_path_buf(), schema.clone());
+        schema
+    }
+
+    fn add_schema_result(&mut self, path: &Path, result: ValidateSchemaResult) {
+        let warnings = result.warning_messages().to_vec();
+        self.add(path, Severity::Warning, "schema", &warnings);
+        self.add(
+            path,
+            Severity::Error,
+            "schema",
+            &result.into_errors().unwrap_or_default(),
+        );
+    }
+
+    fn check_policies(&mut self, path: &Path, schema: Option<&Schema>) {
+        let Some(text) = self.read(path) else {
+            return;
+        };
+        let parse_result = parse_policies(&text);
+        if let Err(errors) = &parse_result {
+            self.add(path, Severity::Error, "syntax", errors);
+            return;
+        }
+        let Some(schema) = schema else {
+            return;
+        };
+        let result = validate_parsed_policies(
+            &Validator::new(schema.clone()),
+            &parse_result,
+            &text,
+            self.mode,
+        );
+        let display_path = self.display_path(path);
+        for diagnostic in result.policy_diagnostics() {
+            let severity = match diagnostic.severity.as_str() {
+                "warning" => Severity::Warning,
+                _ => Severity::Error,
+            };
+            let location = diagnostic
+                .labels
+                .first()
+                .cloned()
+                .unwrap_or_else(|| ValidateMessage::without_span(String::new()));
+            self.findings.push(Finding {
+                message: format!("policy `{}`: {}", diagnostic.policy_id, diagnostic.message),
+                help: diagnostic.help.clone(),
+                ..Finding::new(&display_path, severity, &diagnostic.kind, &location)
+            });
+        }
+    }
+
+    fn check_entities(&mut self, path: &Path, schema: Option<&Schema>) {
+        let Some(text) = self.read(path) else {
+            return;
+        };
+        if let Err(errors) = parse_entities(&text, schema) {
+            let messages: Vec<ValidateMessage> = errors.iter().map(|e| e.to_message()).collect();
+            self.add(path, Severity::Error, "entities", &messages);
+        }
+    }
+
+    fn check_template_links(
+        &mut self,
+        path: &Path,
+        schema: Option<&Schema>,
+        policy_files: &[&PathBuf],
+    ) {
+        let [policies_path] = policy_files else {
+            let message = ValidateMessage::without_span(format!(
+                "template links need exactly one policies file in their directory, found {}",
+                policy_files.len()
+            ));
+            self.add(path, Severity::Error, "templateLinks", &[message]);
+            return;
+        };
+        let (Some(text), Some(schema)) = (self.read(path), schema) else {
+            return;
+        };
+        let policies = fs::read_to_string(policies_path).unwrap_or_default();
+        let result = validate_template_links(schema.clone(), &policies, &text);
+        self.add(
+            path,
+            Severity::Error,
+            "templateLinks",
+            &result.into_errors().unwrap_or_default(),
+        );
+    }
+
+    fn check_request(&mut self, path: &Path, schema: Option<&Schema>) {
+        let Some(text) = self.read(path) else {
+            return;
+        };
+        if let Err(errors) = parse_request(&text, schema) {
+            self.add(path, Severity::Error, "request", &errors);
+        }
+    }
+}
+
+/// Run every validator over the Cedar files under `root`, validating against
+/// `schema_path` when given and otherwise against the single schema in each
+/// file's directory or, failing that, in `root`
+fn lint(root: &Path, schema_path: Option<&Path>, mode: ValidationMode) -> io::Result<Vec<Finding>> {
+    let dirs = find_files(root)?;
+    let mut linter = Linter {
+        root,
+        mode,
+        findings: Vec::new(),
+        schemas: BTreeMap::new(),
+    };
+    let root_schema = dirs
+        .get(root)
+        .and_then(|files| schema_file(files))
+        .map(Path::to_path_buf);
+    for files in dirs.values() {
+        let schema_path = schema_path
+            .map(Path::to_path_buf)
+            .or_else(|| schema_file(files).map(Path::to_path_buf))
+            .or_else(|| root_schema.clone());
+        let schema = schema_path.and_then(|path| linter.schema(&path));
+        // template links refer to the templates of the only policies file in
+        // their directory, as in the editor
+        let policy_files: Vec<&PathBuf> = files
+            .iter()
+            .filter(|(_, kind)| matches!(kind, FileKind::Policies))
+            .map(|(path, _)| path)
+            .collect();
+        for (path, kind) in files {
+            match kind {
+                FileKind::Schema | FileKind::SchemaJson => {
+                    linter.schema(path);
+                }
+                FileKind::Policies => linter.check_policies(path, schema.as_ref()),
+                _ => {}
+            }
+        }
+        for (path, kind) in files {
+            match kind {
+                FileKind::Entities => linter.check_entities(path, schema.as_ref()),
+                FileKind::TemplateLinks => {
+                    linter.check_template_links(path, schema.as_ref(), &policy_files)
+                }
+                FileKind::Request => linter.check_request(path, schema.as_ref()),
+                _ => {}
+            }
+        }
+    }
+    let mut findings = linter.findings;
+    findings.sort_by(|a, b| (&a.path, a.line, a.column).cmp(&(&b.path, b.line, b.column)));
+    Ok(findings)
+}
+
+fn render_human(findings: &[Finding]) -> String {
+    let mut out = String::new();
+    for finding in findings {
+        let severity = match finding.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        out.push_str(&format!(
+            "{}:{}:{}: {severity}[{}]: {}\n",
+            finding.path, finding.line, finding.column, finding.rule, finding.message
+        ));
+        if let Some(help) = &finding.help {
+            out.push_str(&format!("  help: {help}\n"));
+        }
+    }
+    let errors = findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Error)
+        .count();
+    out.push_str(&format!(
+        "{errors} error(s), {} warning(s)\n",
+        findings.len() - errors
+    ));
+    out
+}
+
+fn render_sarif(findings: &[Finding]) -> serde_json::Value {
+    let rules: BTreeSet<&str> = findings
+        .iter()
+        .map(|finding| finding.rule.as_str())
+        .collect();
+    let results: Vec<serde_json::Value> = findings
+        .iter()
+        .map(|finding| {
+            json!({
+                "ruleId": finding.rule,
+                "level": match finding.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                },
+                "message": { "text": finding.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": finding.path },
+                        "region": {
+                            "startLine": finding.line,
+                            "startColumn": finding.column,
+                            "endLine": finding.end_line,
+                            "endColumn": finding.end_column,
+                        }
+                    }
+                }]
+            })
+        })
+        .collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "cedar-lint",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": "https://github.com/cedar-policy/vscode-cedar",
+                    "rules": rules.iter().map(|rule| json!({ "id": rule })).collect::<Vec<_>>(),
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+fn render(findings: &[Finding], format: Format) -> String {
+    match format {
+        Format::Human => render_human(findings),
+        Format::Json => format!("{}\n", json!(findings)),
+        Format::Sarif => format!("{:#}\n", render_sarif(findings)),
+    }
+}
+
+/// Run `cedar-lint` with the process arguments, exiting with 1 when there
+/// are errors and 2 when the arguments or directory are invalid
+pub fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match lint(&options.root, options.schema.as_deref(), options.mode) {
+        Ok(findings) => {
+            print!("{}", render(&findings, options.format));
+            if findings
+                .iter()
+                .any(|finding| finding.severity == Severity::Error)
+            {
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(e) => {
+            eprintln!("error: cannot read `{}`: {e}", options.root.display());
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cedar-lint-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (file, text) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn lint_reports_each_kind_of_file() {
+        let dir = temp_dir(
+            "kinds",
+            &[
+                (
+                    "cedarschema",
+                    "entity User { level: Long };\nentity Doc;\naction view appliesTo { principal: User, resource: Doc };",
+                ),
+                (
+                    "policies.cedar",
+                    "permit (principal, action == Action::\"view\", resource)\nwhen { principal.levl > 3 };",
+                ),
+                ("broken/policies.cedar", "permit (principal,"),
+                (
+                    "users.cedarentities.json",
+                    r#"[{ "uid": { "type": "User", "id": "a" }, "attrs": { "level": "high" }, "parents": [] }]"#,
+                ),
+                (
+                    "view.cedarauth.json",
+                    r#"{ "principal": "User::\"a\"", "action": "Action::\"edit\"", "resource": "Doc::\"d\"", "context": {} }"#,
+                ),
+                ("node_modules/ignored.cedar", "permit ("),
+            ],
+        );
+        let findings = lint(&dir, None, ValidationMode::Strict).unwrap();
+        let found: Vec<(&str, &str, usize)> = findings
+            .iter()
+            .map(|f| (f.path.as_str(), f.rule.as_str(), f.line))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("broken/policies.cedar", "syntax", 1),
+                ("policies.cedar", "unsafeAttributeAccess", 2),
+                ("users.cedarentities.json", "entities", 1),
+                ("view.cedarauth.json", "request", 1),
+            ]
+        );
+        assert!(render(&findings, Format::Human).ends_with("4 error(s), 0 warning(s)\n"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn lint_links_templates_of_their_own_directory() {
+        let schema =
+            "entity User;\nentity Doc;\naction view appliesTo { principal: User, resource: Doc };";
+        let templates = "@id(\"viewer\")\npermit (principal == ?principal, action, resource);";
+        let links = r#"[{ "template_id": "viewer", "link_id": "a", "args": { "?principal": "User::\"a\"" } }]"#;
+        let dir = temp_dir(
+            "links",
+            &[
+                ("cedarschema", schema),
+                ("linked/policies.cedar", templates),
+                ("linked/cedartemplatelinks.json", links),
+                ("other/cedartemplatelinks.json", links),
+                ("other/a.cedar", "permit (principal, action, resource);"),
+                ("other/b.cedar", templates),
+            ],
+        );
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&dir, dir.join("linked/loop")).unwrap();
+        let findings = lint(&dir, None, ValidationMode::Strict).unwrap();
+        let found: Vec<(&str, &str)> = findings
+            .iter()
+            .map(|f| (f.path.as_str(), f.rule.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![("other/cedartemplatelinks.json", "templateLinks")]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn render_sarif_lists_rules_and_regions() {
+        let finding = Finding {
+            path: String::from("policies.cedar"),
+            severity: Severity::Warning,
+            rule: String::from("impossiblePolicy"),
+            message: String::from("policy is impossible"),
+            help: None,
+            line: 2,
+            column: 1,
+            end_line: 2,
+            end_column: 10,
+        };
+        let sarif = render_sarif(&[finding]);
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "impossiblePolicy");
+        assert_eq!(run["results"][0]["level"], "warning");
+        assert_eq!(
+            run["results"][0]["locations"][0]["physicalLocation"]["region"]["endColumn"],
+            10
+        );
+        assert!(parse_args([String::from("--format"), String::from("xml")]).is_err());
+    }
+}
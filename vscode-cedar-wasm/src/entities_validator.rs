@@ -2,18 +2,35 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use cedar_policy::{Entities, Schema};
-use miette::Diagnostic;
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
-use crate::validate_message::{convert_messages_to_js_array, ValidateMessage};
+use crate::entity_checker::{check_entities, group_by_entity, EntityError};
+use crate::utils::to_js_value;
+use crate::validate_message::{
+    convert_messages_to_js_array, messages_from_diagnostic, ValidateMessage,
+};
 
 #[wasm_bindgen(typescript_custom_section)]
 const VALIDATE_ENTITIES_RESULT: &'static str = r#"
+export class EntityError {
+  readonly index: number | undefined;
+  readonly uid: string | undefined;
+  readonly path: Array<string>;
+  readonly message: string;
+  readonly range: SourceRange;
+}
+export class EntityErrors {
+  readonly index: number | undefined;
+  readonly uid: string | undefined;
+  readonly errors: Array<EntityError>;
+}
 export class ValidateEntitiesResult {
   free(): void;
   readonly success: boolean;
   readonly errors: Array<ValidateMessage> | undefined;
+  readonly entityErrors: Array<EntityError> | undefined;
+  readonly errorsByEntity: Array<EntityErrors> | undefined;
 }"#;
 
 #[wasm_bindgen(getter_with_clone, skip_typescript)]
@@ -22,24 +39,101 @@ pub struct ValidateEntitiesResult {
     #[wasm_bindgen(readonly)]
     pub success: bool,
     errors: Option<Vec<ValidateMessage>>,
+    entity_errors: Option<Vec<EntityError>>,
 }
 
 #[wasm_bindgen]
 impl ValidateEntitiesResult {
     #[wasm_bindgen(getter)]
     pub fn errors(&self) -> Option<js_sys::Array> {
-        self.errors.as_ref().map(convert_messages_to_js_array)
+        self.errors.as_deref().map(convert_messages_to_js_array)
+    }
+
+    #[wasm_bindgen(getter, js_name = entityErrors)]
+    pub fn entity_errors(&self) -> JsValue {
+        self.entity_errors
+            .as_ref()
+            .map_or(JsValue::UNDEFINED, to_js_value)
+    }
+
+    #[wasm_bindgen(getter, js_name = errorsByEntity)]
+    pub fn errors_by_entity(&self) -> JsValue {
+        self.entity_errors
+            .as_deref()
+            .map_or(JsValue::UNDEFINED, |errors| {
+                to_js_value(&group_by_entity(errors))
+            })
     }
 }
 
-fn create_error_result(e: impl std::fmt::Display) -> ValidateEntitiesResult {
+pub(crate) fn create_error_result(e: impl std::fmt::Display) -> ValidateEntitiesResult {
     ValidateEntitiesResult {
         success: false,
-        errors: Some(vec![ValidateMessage {
-            message: e.to_string(),
-            offset: 0,
-            length: 0,
-        }]),
+        errors: Some(vec![ValidateMessage::without_span(e.to_string())]),
+        entity_errors: None,
+    }
+}
+
+/// Parse `input_entities_str`, validating it against `schema` when one is
+/// provided. Cedar stops at the first error, so each entity is then checked on
+/// its own to report every error along with where it is in the entities JSON.
+/// Errors about the document as a whole, such as duplicate UIDs, are kept
+/// alongside those about each entity.
+pub(crate) fn parse_entities(
+    input_entities_str: &str,
+    schema: Option<&Schema>,
+) -> Result<Entities, Vec<EntityError>> {
+    Entities::from_json_str(input_entities_str, schema).map_err(|e| {
+        let located = schema
+            .map(|schema| check_entities(input_entities_str, schema))
+            .unwrap_or_default();
+        let mut document = messages_from_diagnostic(&e, input_entities_str);
+        if !located.is_empty() {
+            // Cedar checks the document as a whole only once every entity
+            // conforms, so check it again without the schema
+            if let Err(e) = Entities::from_json_str(input_entities_str, None) {
+                document.extend(messages_from_diagnostic(&e, input_entities_str));
+            }
+        }
+        let mut errors: Vec<EntityError> = Vec::new();
+        for message in document {
+            let seen = located
+                .iter()
+                .chain(&errors)
+                .any(|error| error.message == message.message);
+            if !seen {
+                errors.push(EntityError::document(message));
+            }
+        }
+        errors.extend(located);
+        errors
+    })
+}
+
+/// `parse_entities` with the errors as messages, for results that have no
+/// place for the entity each error is about
+pub(crate) fn parse_entities_messages(
+    input_entities_str: &str,
+    schema: Option<&Schema>,
+) -> Result<Entities, Vec<ValidateMessage>> {
+    parse_entities(input_entities_str, schema)
+        .map_err(|errors| errors.iter().map(EntityError::to_message).collect())
+}
+
+pub(crate) fn entities_result(
+    parse_result: &Result<Entities, Vec<EntityError>>,
+) -> ValidateEntitiesResult {
+    match parse_result {
+        Ok(_entities) => ValidateEntitiesResult {
+            success: true,
+            errors: None,
+            entity_errors: None,
+        },
+        Err(errors) => ValidateEntitiesResult {
+            success: false,
+            errors: Some(errors.iter().map(EntityError::to_message).collect()),
+            entity_errors: Some(errors.clone()),
+        },
     }
 }
 
@@ -48,38 +142,13 @@ pub fn validate_entities_schema_json(
     input_schema_str: &str,
     input_entities_str: &str,
 ) -> ValidateEntitiesResult {
-    let schema = match Schema::from_json_str(&input_schema_str) {
+    let schema = match Schema::from_json_str(input_schema_str) {
         Ok(schema) => Some(schema),
         Err(e) => {
             return create_error_result(e);
         }
     };
-    let result = match Entities::from_json_str(input_entities_str, schema.as_ref()) {
-        Ok(_entities) => ValidateEntitiesResult {
-            success: true,
-            errors: None,
-        },
-        Err(e) => {
-            let diagnostic: &dyn Diagnostic = &e;
-            let validate_errs = match diagnostic.source() {
-                Some(source) => vec![ValidateMessage {
-                    message: String::from(&format!("{}: {}", e, source)),
-                    offset: 0,
-                    length: 0,
-                }],
-                None => vec![ValidateMessage {
-                    message: String::from(&format!("{e}")),
-                    offset: 0,
-                    length: 0,
-                }],
-            };
-            ValidateEntitiesResult {
-                success: false,
-                errors: Some(validate_errs),
-            }
-        }
-    };
-    result
+    entities_result(&parse_entities(input_entities_str, schema.as_ref()))
 }
 
 #[wasm_bindgen(js_name = validateEntitiesSchemaCedar)]
@@ -87,38 +156,16 @@ pub fn validate_entities_schema_cedar(
     input_schema_str: &str,
     input_entities_str: &str,
 ) -> ValidateEntitiesResult {
-    let schema = match Schema::from_cedarschema_str(&input_schema_str) {
+    let schema = match Schema::from_cedarschema_str(input_schema_str) {
         Ok(schema) => Some(schema),
         Err(e) => {
             return create_error_result(e);
         }
     };
-    let result = match Entities::from_json_str(input_entities_str, Some(&schema.unwrap().0)) {
-        Ok(_entities) => ValidateEntitiesResult {
-            success: true,
-            errors: None,
-        },
-        Err(e) => {
-            let diagnostic: &dyn Diagnostic = &e;
-            let validate_errs = match diagnostic.source() {
-                Some(source) => vec![ValidateMessage {
-                    message: String::from(&format!("{}: {}", e, source)),
-                    offset: 0,
-                    length: 0,
-                }],
-                None => vec![ValidateMessage {
-                    message: String::from(&format!("{e}")),
-                    offset: 0,
-                    length: 0,
-                }],
-            };
-            ValidateEntitiesResult {
-                success: false,
-                errors: Some(validate_errs),
-            }
-        }
-    };
-    result
+    entities_result(&parse_entities(
+        input_entities_str,
+        Some(&schema.unwrap().0),
+    ))
 }
 
 #[cfg(test)]
@@ -147,7 +194,78 @@ mod test {
             ValidateEntitiesResult {
                 success: false,
                 errors: _,
+                entity_errors: _,
             }
         ));
     }
+
+    #[test]
+    fn validate_entities_locates_error() {
+        let entities_str = fs::read_to_string("../testdata/entityattr/mismatch.cedarentities.json")
+            .expect("Failed to read entities file");
+        let result = validate_entities_schema_json(get_schema(), &entities_str);
+        assert!(!result.success);
+        let entity_errors = result.entity_errors.unwrap();
+        assert_eq!(entity_errors.len(), 1);
+        let error = &entity_errors[0];
+        assert_eq!(error.uid.as_deref(), Some(r#"Test::"mismatch""#));
+        assert_eq!(error.path, vec!["attrs", "test"]);
+        assert!(error
+            .message
+            .starts_with("entity does not conform to the schema: in attribute `test`"));
+        assert_eq!(
+            &entities_str[error.range.offset..][..error.range.length],
+            "1"
+        );
+        assert_eq!(result.errors.unwrap()[0].offset, error.range.offset);
+    }
+
+    #[test]
+    fn validate_entities_reports_every_error() {
+        let entities_str = r#"[
+  { "uid": { "type": "Test", "id": "a" }, "attrs": { "test": 1, "extra": true }, "parents": [] },
+  { "uid": { "type": "Test", "id": "b" }, "attrs": { "test": "b" }, "parents": [] },
+  { "uid": { "type": "Test", "id": "c" }, "attrs": {}, "parents": [{ "type": "Other", "id": "d" }] }
+]"#;
+        let result = validate_entities_schema_json(get_schema(), entities_str);
+        assert!(!result.success);
+        let entity_errors = result.entity_errors.unwrap();
+        let paths: Vec<_> = entity_errors
+            .iter()
+            .map(|error| (error.index, error.path.join(".")))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                (Some(0), String::from("attrs.test")),
+                (Some(0), String::from("attrs.extra")),
+                (Some(2), String::from("attrs.test")),
+                (Some(2), String::from("parents.0")),
+            ]
+        );
+        assert_eq!(result.errors.unwrap().len(), 4);
+
+        let groups = group_by_entity(&entity_errors);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].uid.as_deref(), Some(r#"Test::"a""#));
+        assert_eq!(groups[1].index, Some(2));
+        assert_eq!(groups[1].errors.len(), 2);
+    }
+
+    #[test]
+    fn validate_entities_reports_document_and_entity_errors() {
+        let entities_str = r#"[
+  { "uid": { "type": "Test", "id": "a" }, "attrs": { "test": "a" }, "parents": [] },
+  { "uid": { "type": "Test", "id": "a" }, "attrs": { "test": "b" }, "parents": [] },
+  { "uid": { "type": "Test", "id": "b" }, "attrs": { "test": 1 }, "parents": [] }
+]"#;
+        let result = validate_entities_schema_json(get_schema(), entities_str);
+        assert!(!result.success);
+        let entity_errors = result.entity_errors.unwrap();
+        assert_eq!(entity_errors.len(), 2);
+        assert_eq!(entity_errors[0].index, None);
+        assert!(entity_errors[0].message.contains("duplicate entity entry"));
+        assert_eq!(entity_errors[1].index, Some(2));
+        assert_eq!(entity_errors[1].path, vec!["attrs", "test"]);
+    }
 }
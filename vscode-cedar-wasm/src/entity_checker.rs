@@ -0,0 +1,518 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// `Entities::from_json_str` reports errors without a location, so this module
+// repeats its checks one part of one entity at a time to find where in the
+// entities JSON each error is. The tests below check that it finds the same
+// error Cedar reports for each kind of nonconforming entity.
+
+use cedar_policy::Schema;
+use cedar_policy_core::ast::{Eid, Entity, EntityType, EntityUID, PartialValue};
+use cedar_policy_core::entities::conformance::err::{
+    EntitySchemaConformanceError, UnexpectedEntityTypeError,
+};
+use cedar_policy_core::entities::conformance::{validate_euid, EntitySchemaConformanceChecker};
+use cedar_policy_core::entities::err::EntitiesError;
+use cedar_policy_core::entities::json::err::{
+    JsonDeserializationError, JsonDeserializationErrorContext,
+};
+use cedar_policy_core::entities::json::ValueParser;
+use cedar_policy_core::entities::{
+    EntityJson, EntityJsonParser, EntityTypeDescription, EntityUidJson, Schema as _, SchemaType,
+    TCComputation,
+};
+use cedar_policy_core::extensions::Extensions;
+use cedar_policy_core::validator::CoreSchema;
+use nonempty::NonEmpty;
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::json_spans::JsonNode;
+use crate::validate_message::{diagnostic_message, SourceRange, ValidateMessage};
+
+/// An error in an entities JSON document, with the index and UID of the
+/// entity it is about and the path within that entity, such as
+/// `["attrs", "jobLevel"]`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EntityError {
+    pub index: Option<usize>,
+    pub uid: Option<String>,
+    pub path: Vec<String>,
+    pub message: String,
+    pub range: SourceRange,
+}
+
+impl EntityError {
+    /// Error about the whole document rather than one entity
+    pub fn document(message: ValidateMessage) -> Self {
+        EntityError {
+            index: None,
+            uid: None,
+            path: Vec::new(),
+            range: SourceRange {
+                offset: message.offset,
+                length: message.length,
+                start_line: message.start_line,
+                start_character: message.start_character,
+                end_line: message.end_line,
+                end_character: message.end_character,
+            },
+            message: message.message,
+        }
+    }
+
+    pub fn to_message(&self) -> ValidateMessage {
+        ValidateMessage {
+            message: self.message.clone(),
+            offset: self.range.offset,
+            length: self.range.length,
+            start_line: self.range.start_line,
+            start_character: self.range.start_character,
+            end_line: self.range.end_line,
+            end_character: self.range.end_character,
+        }
+    }
+}
+
+/// Entity type description that requires only the attributes in `required`,
+/// so each attribute can be checked without the others present
+struct RequiredSubset<'a, D> {
+    desc: &'a D,
+    required: Option<SmolStr>,
+}
+
+impl<D: EntityTypeDescription> EntityTypeDescription for RequiredSubset<'_, D> {
+    fn entity_type(&self) -> EntityType {
+        self.desc.entity_type()
+    }
+
+    fn attr_type(&self, attr: &str) -> Option<SchemaType> {
+        self.desc.attr_type(attr)
+    }
+
+    fn tag_type(&self) -> Option<SchemaType> {
+        self.desc.tag_type()
+    }
+
+    fn required_attrs<'s>(&'s self) -> Box<dyn Iterator<Item = SmolStr> + 's> {
+        Box::new(self.required.iter().cloned())
+    }
+
+    fn allowed_parent_types(&self) -> Arc<HashSet<EntityType>> {
+        self.desc.allowed_parent_types()
+    }
+
+    fn open_attributes(&self) -> bool {
+        self.desc.open_attributes()
+    }
+
+    fn enum_entity_eids(&self) -> Option<NonEmpty<Eid>> {
+        self.desc.enum_entity_eids()
+    }
+}
+
+/// The errors about one entity, or about the whole document when `index` is
+/// `None`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EntityErrors {
+    pub index: Option<usize>,
+    pub uid: Option<String>,
+    pub errors: Vec<EntityError>,
+}
+
+/// Group `errors`, which are in document order, by the entity they are about
+pub fn group_by_entity(errors: &[EntityError]) -> Vec<EntityErrors> {
+    let mut groups: Vec<EntityErrors> = Vec::new();
+    for error in errors {
+        match groups.last_mut() {
+            Some(group) if group.index == error.index => {
+                if group.uid.is_none() {
+                    group.uid = error.uid.clone();
+                }
+                group.errors.push(error.clone());
+            }
+            _ => groups.push(EntityErrors {
+                index: error.index,
+                uid: error.uid.clone(),
+                errors: vec![error.clone()],
+            }),
+        }
+    }
+    groups
+}
+
+struct Errors<'a> {
+    src: &'a str,
+    /// Index of the entity being checked
+    index: usize,
+    errors: Vec<EntityError>,
+}
+
+impl Errors<'_> {
+    fn push(
+        &mut self,
+        uid: Option<&EntityUID>,
+        path: &[&str],
+        node: &JsonNode,
+        error: impl Into<EntitiesError>,
+    ) {
+        self.errors.push(EntityError {
+            index: Some(self.index),
+            uid: uid.map(ToString::to_string),
+            path: path.iter().map(ToString::to_string).collect(),
+            message: diagnostic_message(&error.into()),
+            range: SourceRange::new(self.src, node.offset, node.length),
+        });
+    }
+}
+
+struct EntityChecker<'a> {
+    schema: CoreSchema<'a>,
+    extensions: &'a Extensions<'a>,
+}
+
+impl EntityChecker<'_> {
+    fn check_entity(&self, errors: &mut Errors, value: &serde_json::Value, node: &JsonNode) {
+        if let Err(e) = serde_json::from_value::<EntityJson>(value.clone()) {
+            errors.push(None, &[], node, JsonDeserializationError::from(e));
+            return;
+        }
+        let uid_node = node.get("uid").unwrap_or(node);
+        let uid = match serde_json::from_value::<EntityUidJson>(value["uid"].clone()) {
+            Ok(uid) => match uid.into_euid(&|| JsonDeserializationErrorContext::EntityUid) {
+                Ok(uid) => uid,
+                Err(e) => {
+                    errors.push(None, &["uid"], uid_node, e);
+                    return;
+                }
+            },
+            Err(e) => {
+                errors.push(None, &["uid"], uid_node, JsonDeserializationError::from(e));
+                return;
+            }
+        };
+        if uid.entity_type().is_action() {
+            // actions must match their declaration in the schema as a whole
+            let parser = EntityJsonParser::new(
+                Some(&self.schema),
+                self.extensions,
+                TCComputation::AssumeAlreadyComputed,
+            );
+            if let Err(e) = parser.single_from_json_value(value.clone()) {
+                errors.push(Some(&uid), &[], node, e);
+            }
+            return;
+        }
+        let Some(desc) = self.schema.entity_type(uid.entity_type()) else {
+            let suggested_types = self
+                .schema
+                .entity_types_with_basename(&uid.entity_type().name().basename())
+                .collect();
+            let e = JsonDeserializationError::from(EntitySchemaConformanceError::from(
+                UnexpectedEntityTypeError {
+                    uid: uid.clone(),
+                    suggested_types,
+                },
+            ));
+            errors.push(Some(&uid), &["uid"], uid_node, e);
+            return;
+        };
+        if let Err(e) = validate_euid(&self.schema, &uid) {
+            errors.push(
+                Some(&uid),
+                &["uid"],
+                uid_node,
+                EntitySchemaConformanceError::from(e),
+            );
+        }
+        self.check_attributes(errors, &uid, &desc, value, node);
+        self.check_tags(errors, &uid, &desc, value, node);
+        self.check_parents(errors, &uid, &desc, value, node);
+    }
+
+    fn check_attributes(
+        &self,
+        errors: &mut Errors,
+        uid: &EntityUID,
+        desc: &impl EntityTypeDescription,
+        value: &serde_json::Value,
+        node: &JsonNode,
+    ) {
+        let checker = EntitySchemaConformanceChecker::new(&self.schema, self.extensions);
+        let attrs_node = node.get("attrs").unwrap_or(node);
+        let attrs = value["attrs"].as_object().cloned().unwrap_or_default();
+        for required in desc.required_attrs() {
+            if attrs.contains_key(required.as_str()) {
+                continue;
+            }
+            let subset = RequiredSubset {
+                desc,
+                required: Some(required.clone()),
+            };
+            if let Err(e) = checker.validate_entity_attributes(uid, std::iter::empty(), &subset) {
+                errors.push(Some(uid), &["attrs", &required], attrs_node, e);
+            }
+        }
+        let subset = RequiredSubset {
+            desc,
+            required: None,
+        };
+        for (attr, attr_value) in attrs {
+            let attr_node = attrs_node.get(&attr).unwrap_or(attrs_node);
+            let path = ["attrs", attr.as_str()];
+            let attr = SmolStr::from(attr.as_str());
+            let expected_ty = desc.attr_type(&attr);
+            if expected_ty.is_none() && !desc.open_attributes() {
+                // any value gets the same error, which Cedar reports while
+                // parsing the entity
+                let placeholder = PartialValue::from(true);
+                if let Err(e) = checker.validate_entity_attributes(
+                    uid,
+                    std::iter::once((&attr, &placeholder)),
+                    &subset,
+                ) {
+                    errors.push(
+                        Some(uid),
+                        &path,
+                        attr_node,
+                        JsonDeserializationError::from(e),
+                    );
+                }
+                continue;
+            }
+            let expr = match ValueParser::new(self.extensions).val_into_restricted_expr(
+                attr_value,
+                expected_ty.as_ref(),
+                &|| JsonDeserializationErrorContext::EntityAttribute {
+                    uid: uid.clone(),
+                    attr: attr.clone(),
+                },
+            ) {
+                Ok(expr) => expr,
+                Err(e) => {
+                    errors.push(Some(uid), &path, attr_node, e);
+                    continue;
+                }
+            };
+            match Entity::new(
+                uid.clone(),
+                [(attr.clone(), expr)],
+                HashSet::new(),
+                HashSet::new(),
+                [],
+                self.extensions,
+            ) {
+                Ok(entity) => {
+                    if let Err(e) = checker.validate_entity_attributes(uid, entity.attrs(), &subset)
+                    {
+                        errors.push(Some(uid), &path, attr_node, e);
+                    }
+                }
+                Err(e) => errors.push(
+                    Some(uid),
+                    &path,
+                    attr_node,
+                    JsonDeserializationError::from(e),
+                ),
+            }
+        }
+    }
+
+    fn check_tags(
+        &self,
+        errors: &mut Errors,
+        uid: &EntityUID,
+        desc: &impl EntityTypeDescription,
+        value: &serde_json::Value,
+        node: &JsonNode,
+    ) {
+        let checker = EntitySchemaConformanceChecker::new(&self.schema, self.extensions);
+        let tags_node = node.get("tags").unwrap_or(node);
+        let tags = value["tags"].as_object().cloned().unwrap_or_default();
+        for (tag, tag_value) in tags {
+            let tag_node = tags_node.get(&tag).unwrap_or(tags_node);
+            let path = ["tags", tag.as_str()];
+            let tag = SmolStr::from(tag.as_str());
+            let Some(expected_ty) = desc.tag_type() else {
+                let placeholder = PartialValue::from(true);
+                if let Err(e) =
+                    checker.validate_tags(uid, std::iter::once((&tag, &placeholder)), desc)
+                {
+                    errors.push(
+                        Some(uid),
+                        &path,
+                        tag_node,
+                        JsonDeserializationError::from(e),
+                    );
+                }
+                continue;
+            };
+            let expr = match ValueParser::new(self.extensions).val_into_restricted_expr(
+                tag_value,
+                Some(&expected_ty),
+                &|| JsonDeserializationErrorContext::EntityTag {
+                    uid: uid.clone(),
+                    tag: tag.clone(),
+                },
+            ) {
+                Ok(expr) => expr,
+                Err(e) => {
+                    errors.push(Some(uid), &path, tag_node, e);
+                    continue;
+                }
+            };
+            match Entity::new(
+                uid.clone(),
+                [],
+                HashSet::new(),
+                HashSet::new(),
+                [(tag.clone(), expr)],
+                self.extensions,
+            ) {
+                Ok(entity) => {
+                    if let Err(e) = checker.validate_tags(uid, entity.tags(), desc) {
+                        errors.push(Some(uid), &path, tag_node, e);
+                    }
+                }
+                Err(e) => errors.push(
+                    Some(uid),
+                    &path,
+                    tag_node,
+                    JsonDeserializationError::from(e),
+                ),
+            }
+        }
+    }
+
+    fn check_parents(
+        &self,
+        errors: &mut Errors,
+        uid: &EntityUID,
+        desc: &impl EntityTypeDescription,
+        value: &serde_json::Value,
+        node: &JsonNode,
+    ) {
+        let checker = EntitySchemaConformanceChecker::new(&self.schema, self.extensions);
+        let parents_node = node.get("parents").unwrap_or(node);
+        let parents = value["parents"].as_array().cloned().unwrap_or_default();
+        for (index, parent) in parents.into_iter().enumerate() {
+            let parent_node = parents_node.elements().get(index).unwrap_or(parents_node);
+            let index = index.to_string();
+            let path = ["parents", index.as_str()];
+            let parent = match serde_json::from_value::<EntityUidJson>(parent) {
+                Ok(parent) => parent,
+                Err(e) => {
+                    errors.push(
+                        Some(uid),
+                        &path,
+                        parent_node,
+                        JsonDeserializationError::from(e),
+                    );
+                    continue;
+                }
+            };
+            match parent
+                .into_euid(&|| JsonDeserializationErrorContext::EntityParents { uid: uid.clone() })
+            {
+                Ok(parent) => {
+                    if let Err(e) =
+                        checker.validate_entity_ancestors(uid, std::iter::once(&parent), desc)
+                    {
+                        errors.push(Some(uid), &path, parent_node, e);
+                    }
+                }
+                Err(e) => errors.push(Some(uid), &path, parent_node, e),
+            }
+        }
+    }
+}
+
+/// Check each entity in `src` against `schema` on its own, returning the
+/// errors found in document order
+pub(crate) fn check_entities(src: &str, schema: &Schema) -> Vec<EntityError> {
+    let (Ok(serde_json::Value::Array(values)), Some(root)) = (
+        serde_json::from_str::<serde_json::Value>(src),
+        JsonNode::parse(src),
+    ) else {
+        return Vec::new();
+    };
+    let checker = EntityChecker {
+        schema: CoreSchema::new(schema.as_ref()),
+        extensions: Extensions::all_available(),
+    };
+    let mut errors = Errors {
+        src,
+        index: 0,
+        errors: Vec::new(),
+    };
+    for (index, (value, node)) in values.iter().zip(root.elements()).enumerate() {
+        errors.index = index;
+        checker.check_entity(&mut errors, value, node);
+    }
+    let mut errors = errors.errors;
+    errors.sort_by_key(|error| error.range.offset);
+    errors
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cedar_policy::Entities;
+
+    const SCHEMA: &str = r#"
+entity Group;
+entity User in [Group] {
+  name: String,
+  age?: Long,
+  address?: { street: String },
+  ip?: ipaddr,
+} tags String;
+entity Color enum ["red", "green"];
+action view appliesTo { principal: User, resource: Group };
+"#;
+
+    /// Documents with a single entity, so that Cedar's only error is the one
+    /// the checker should find. Only the first conforms to the schema.
+    const ENTITIES: &[&str] = &[
+        r#"{ "uid": { "type": "User", "id": "ok" }, "attrs": { "name": "a", "ip": { "__extn": { "fn": "ip", "arg": "10.0.0.1" } } }, "parents": [{ "type": "Group", "id": "g" }], "tags": { "t": "v" } }"#,
+        r#"{ "uid": { "type": "User", "id": "a" }, "attrs": { "name": 1 }, "parents": [] }"#,
+        r#"{ "uid": { "type": "User", "id": "a" }, "attrs": {}, "parents": [] }"#,
+        r#"{ "uid": { "type": "User", "id": "a" }, "attrs": { "name": "a", "extra": true }, "parents": [] }"#,
+        r#"{ "uid": { "type": "User", "id": "a" }, "attrs": { "name": "a", "address": { "street": 1 } }, "parents": [] }"#,
+        r#"{ "uid": { "type": "User", "id": "a" }, "attrs": { "name": "a", "ip": { "__extn": { "fn": "ip", "arg": "nope" } } }, "parents": [] }"#,
+        r#"{ "uid": { "type": "User", "id": "a" }, "attrs": { "name": "a" }, "parents": [{ "type": "User", "id": "b" }] }"#,
+        r#"{ "uid": { "type": "User", "id": "a" }, "attrs": { "name": "a" }, "parents": [], "tags": { "t": 1 } }"#,
+        r#"{ "uid": { "type": "Group", "id": "g" }, "attrs": {}, "parents": [], "tags": { "t": "v" } }"#,
+        r#"{ "uid": { "type": "Other", "id": "a" }, "attrs": {}, "parents": [] }"#,
+        r#"{ "uid": { "type": "Color", "id": "blue" }, "attrs": {}, "parents": [] }"#,
+        r#"{ "uid": { "type": "Action", "id": "view" }, "attrs": {}, "parents": [{ "type": "Action", "id": "edit" }] }"#,
+    ];
+
+    #[test]
+    fn check_entities_agrees_with_cedar() {
+        let (schema, _warnings) = Schema::from_cedarschema_str(SCHEMA).unwrap();
+        for (index, entity) in ENTITIES.iter().enumerate() {
+            let src = format!("[{entity}]");
+            let located: Vec<String> = check_entities(&src, &schema)
+                .into_iter()
+                .map(|error| error.message)
+                .collect();
+            match Entities::from_json_str(&src, Some(&schema)) {
+                Ok(_) => {
+                    assert_eq!(index, 0, "{entity}");
+                    assert!(located.is_empty(), "{entity}: {located:?}");
+                }
+                Err(e) => {
+                    assert_ne!(index, 0, "{entity}: {e}");
+                    let message = diagnostic_message(&e);
+                    assert!(
+                        located.contains(&message),
+                        "{entity}: {message}, {located:?}"
+                    );
+                }
+            }
+        }
+    }
+}
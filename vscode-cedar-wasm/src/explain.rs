@@ -0,0 +1,396 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use cedar_policy::{
+    eval_expression, Authorizer, Entities, EvalResult, Expression, Policy, PolicyId, Request,
+    Schema,
+};
+use cedar_policy_core::{ast, est};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::authorizer::{decision_name, parse_request, policy_name};
+use crate::entities_validator::parse_entities_messages;
+use crate::policy_outline::{parse_policy_outline, PolicyOutline};
+use crate::syntax_validator::parse_policies;
+use crate::utils::to_js_value;
+use crate::validate_message::{convert_messages_to_js_array, SourceRange, ValidateMessage};
+
+#[wasm_bindgen(typescript_custom_section)]
+const EXPLAIN_AUTHORIZATION_RESULT: &'static str = r#"
+export class PolicyTrace {
+  readonly policyId: string;
+  readonly cedarId: string;
+  readonly effect: "permit" | "forbid";
+  readonly status: "scope-mismatch" | "condition-false" | "satisfied" | "errored";
+  readonly range: SourceRange;
+  readonly error: string | undefined;
+}
+export class ExplainAuthorizationResult {
+  free(): void;
+  readonly success: boolean;
+  readonly decision: "allow" | "deny" | undefined;
+  readonly determiningPolicies: Array<string> | undefined;
+  readonly policies: Array<PolicyTrace> | undefined;
+  readonly errors: Array<ValidateMessage> | undefined;
+}"#;
+
+#[wasm_bindgen(getter_with_clone, skip_typescript)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExplainAuthorizationResult {
+    #[wasm_bindgen(readonly)]
+    pub success: bool,
+    #[wasm_bindgen(readonly)]
+    pub decision: Option<String>,
+    determining_policies: Option<Vec<String>>,
+    policies: Option<Vec<PolicyTrace>>,
+    errors: Option<Vec<ValidateMessage>>,
+}
+
+#[wasm_bindgen]
+impl ExplainAuthorizationResult {
+    #[wasm_bindgen(getter, js_name = determiningPolicies)]
+    pub fn determining_policies(&self) -> Option<js_sys::Array> {
+        self.determining_policies
+            .as_ref()
+            .map(|ids| ids.iter().map(|id| JsValue::from_str(id)).collect())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn policies(&self) -> JsValue {
+        self.policies
+            .as_ref()
+            .map_or(JsValue::UNDEFINED, to_js_value)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Option<js_sys::Array> {
+        self.errors.as_deref().map(convert_messages_to_js_array)
+    }
+}
+
+/// Why a policy did or did not apply to a request, where `range` is the
+/// scope constraint or condition that did not hold or errored, or the whole
+/// policy when it was satisfied
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyTrace {
+    pub policy_id: String,
+    pub cedar_id: String,
+    pub effect: String,
+    pub status: String,
+    pub range: SourceRange,
+    pub error: Option<String>,
+}
+
+fn create_error_result(errors: Vec<ValidateMessage>) -> ExplainAuthorizationResult {
+    ExplainAuthorizationResult {
+        success: false,
+        decision: None,
+        determining_policies: None,
+        policies: None,
+        errors: Some(errors),
+    }
+}
+
+/// Evaluate an expression of a parsed policy for the request, with errors
+/// as text
+fn eval_bool(expr: ast::Expr, request: &Request, entities: &Entities) -> Result<bool, String> {
+    match eval_expression(request, entities, &Expression::from(expr)).map_err(|e| e.to_string())? {
+        EvalResult::Bool(value) => Ok(value),
+        value => Err(format!("expected a boolean but saw `{value}`")),
+    }
+}
+
+/// The `when` and `unless` clauses of `policy` in source order, paired with
+/// whether they are `when` clauses. The policy AST joins all conditions into
+/// one expression, so they are taken from the EST form of the policy instead.
+fn policy_conditions(policy: &Policy) -> Result<Vec<(bool, ast::Expr)>, String> {
+    let mut json = policy.to_json().map_err(|e| e.to_string())?;
+    let clauses: Vec<est::Clause> =
+        serde_json::from_value(json["conditions"].take()).map_err(|e| e.to_string())?;
+    clauses
+        .into_iter()
+        .map(|clause| {
+            let (is_when, expr) = match clause {
+                est::Clause::When(expr) => (true, expr),
+                est::Clause::Unless(expr) => (false, expr),
+            };
+            expr.try_into_ast(policy.id().as_ref())
+                .map(|expr| (is_when, expr))
+                .map_err(|e| e.to_string())
+        })
+        .collect()
+}
+
+/// Evaluate the scope constraints and then the conditions of `policy` in
+/// order, as the authorizer does, stopping at the first that does not hold.
+/// `outline` only supplies the ranges to report.
+fn trace_policy(
+    outline: &PolicyOutline,
+    policy: &Policy,
+    request: &Request,
+    entities: &Entities,
+) -> PolicyTrace {
+    let trace = |status: &str, range: &SourceRange, error: Option<String>| PolicyTrace {
+        policy_id: outline.policy_id.clone(),
+        cedar_id: outline.cedar_id.clone(),
+        effect: outline.effect.clone(),
+        status: status.to_string(),
+        range: range.clone(),
+        error,
+    };
+    let ast: &ast::Policy = policy.as_ref();
+    let scope = [
+        ("principal", ast.principal_constraint().as_expr()),
+        ("action", ast.action_constraint().as_expr()),
+        ("resource", ast.resource_constraint().as_expr()),
+    ];
+    for (variable, expr) in scope {
+        let range = outline
+            .scope
+            .iter()
+            .find(|scope| scope.variable == variable)
+            .map_or(&outline.range, |scope| &scope.range);
+        match eval_bool(expr, request, entities) {
+            Ok(true) => {}
+            Ok(false) => return trace("scope-mismatch", range, None),
+            Err(e) => return trace("errored", range, Some(e)),
+        }
+    }
+    let conditions = match policy_conditions(policy) {
+        Ok(conditions) => conditions,
+        Err(e) => return trace("errored", &outline.range, Some(e)),
+    };
+    for (index, (is_when, expr)) in conditions.into_iter().enumerate() {
+        let range = outline
+            .conditions
+            .get(index)
+            .map_or(&outline.range, |condition| &condition.range);
+        match eval_bool(expr, request, entities) {
+            Ok(value) if value == is_when => {}
+            Ok(_) => return trace("condition-false", range, None),
+            Err(e) => return trace("errored", range, Some(e)),
+        }
+    }
+    trace("satisfied", &outline.range, None)
+}
+
+/// Authorize the request as `isAuthorized` does and trace every policy, but
+/// not templates, which only apply through links
+fn explain_authorization(
+    schema: Option<&Schema>,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_request_str: &str,
+) -> ExplainAuthorizationResult {
+    let pset = match parse_policies(input_policies_str) {
+        Ok(pset) => pset,
+        Err(errors) => return create_error_result(errors),
+    };
+    let entities = match parse_entities_messages(input_entities_str, schema) {
+        Ok(entities) => entities,
+        Err(errors) => return create_error_result(errors),
+    };
+    let request = match parse_request(input_request_str, schema) {
+        Ok(request) => request,
+        Err(errors) => return create_error_result(errors),
+    };
+
+    let response = Authorizer::new().is_authorized(&request, &pset, &entities);
+    let policies = parse_policy_outline(input_policies_str)
+        .into_policies()
+        .unwrap_or_default()
+        .iter()
+        .filter(|outline| outline.kind == "policy")
+        .filter_map(|outline| {
+            let policy = pset.policy(&PolicyId::new(&outline.cedar_id))?;
+            Some(trace_policy(outline, policy, &request, &entities))
+        })
+        .collect();
+    ExplainAuthorizationResult {
+        success: true,
+        decision: Some(decision_name(response.decision()).to_string()),
+        determining_policies: Some(
+            response
+                .diagnostics()
+                .reason()
+                .map(|id| policy_name(&pset, id))
+                .collect(),
+        ),
+        policies: Some(policies),
+        errors: None,
+    }
+}
+
+#[wasm_bindgen(js_name = explainAuthorization)]
+pub fn explain_authorization_no_schema(
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_request_str: &str,
+) -> ExplainAuthorizationResult {
+    explain_authorization(
+        None,
+        input_policies_str,
+        input_entities_str,
+        input_request_str,
+    )
+}
+
+#[wasm_bindgen(js_name = explainAuthorizationSchemaJSON)]
+pub fn explain_authorization_schema_json(
+    input_schema_str: &str,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_request_str: &str,
+) -> ExplainAuthorizationResult {
+    match Schema::from_json_str(input_schema_str) {
+        Ok(schema) => explain_authorization(
+            Some(&schema),
+            input_policies_str,
+            input_entities_str,
+            input_request_str,
+        ),
+        Err(e) => create_error_result(vec![ValidateMessage::without_span(e.to_string())]),
+    }
+}
+
+#[wasm_bindgen(js_name = explainAuthorizationSchemaCedar)]
+pub fn explain_authorization_schema_cedar(
+    input_schema_str: &str,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_request_str: &str,
+) -> ExplainAuthorizationResult {
+    match Schema::from_cedarschema_str(input_schema_str) {
+        Ok((schema, _warnings)) => explain_authorization(
+            Some(&schema),
+            input_policies_str,
+            input_entities_str,
+            input_request_str,
+        ),
+        Err(e) => create_error_result(vec![ValidateMessage::without_span(e.to_string())]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cedar_policy::Decision;
+
+    const POLICIES: &str = r#"@id("admins")
+permit (principal in Group::"admins", action, resource);
+
+@id("levels")
+permit (principal, action == Action::"view", resource)
+when { principal.level > 3 }
+unless { resource.locked };
+
+@id("missing")
+forbid (principal, action, resource)
+when { principal.missing };
+
+permit (principal, action == Action::"edit", resource);
+
+permit (principal == ?principal, action, resource);
+"#;
+
+    const ENTITIES: &str = r#"[
+        { "uid": { "type": "User", "id": "alice" }, "attrs": { "level": 5 }, "parents": [] },
+        { "uid": { "type": "Doc", "id": "doc" }, "attrs": { "locked": true }, "parents": [] }
+    ]"#;
+
+    const REQUEST: &str = r#"{ "principal": "User::\"alice\"", "action": "Action::\"view\"", "resource": "Doc::\"doc\"", "context": {} }"#;
+
+    #[test]
+    fn explain_authorization_traces_each_policy() {
+        let result = explain_authorization_no_schema(POLICIES, ENTITIES, REQUEST);
+        assert!(result.success);
+        assert_eq!(result.decision, Some(String::from("deny")));
+        let policies = result.policies.unwrap();
+        let statuses: Vec<(&str, &str)> = policies
+            .iter()
+            .map(|policy| (policy.policy_id.as_str(), policy.status.as_str()))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("admins", "scope-mismatch"),
+                ("levels", "condition-false"),
+                ("missing", "errored"),
+                ("policy3", "scope-mismatch"),
+            ]
+        );
+        assert_eq!(policies[0].range.start_character, 8);
+        assert_eq!(policies[1].range.start_line, 6);
+        assert!(policies[2].error.is_some());
+        assert_eq!(policies[3].range.start_line, 12);
+    }
+
+    #[test]
+    fn explain_authorization_reports_satisfied_policies() {
+        let entities = ENTITIES.replace("true", "false");
+        let result = explain_authorization_no_schema(POLICIES, &entities, REQUEST);
+        assert_eq!(result.decision, Some(String::from("allow")));
+        assert_eq!(
+            result.determining_policies,
+            Some(vec![String::from("levels")])
+        );
+        let levels = &result.policies.unwrap()[1];
+        assert_eq!(levels.status, "satisfied");
+        assert_eq!(levels.range.start_line, 3);
+
+        assert!(!explain_authorization_no_schema("permit(", "[]", REQUEST).success);
+    }
+
+    #[test]
+    fn explain_authorization_agrees_with_authorizer_reasons() {
+        let policies = r#"permit (principal is User in Group::"staff", action, resource)
+when // a { in a comment
+{ principal.level > 3 };
+
+permit (principal, action, resource is Doc)
+unless { resource.locked }
+when { context.ok };
+
+forbid (principal, action in [Action::"view", Action::"edit"], resource)
+when { principal.level == 5 }
+unless { context.ok };
+"#;
+        let entities_str = r#"[
+            { "uid": { "type": "User", "id": "alice" }, "attrs": { "level": 5 }, "parents": [{ "type": "Group", "id": "staff" }] },
+            { "uid": { "type": "Group", "id": "staff" }, "attrs": {}, "parents": [] },
+            { "uid": { "type": "Doc", "id": "doc" }, "attrs": { "locked": false }, "parents": [] }
+        ]"#;
+        let pset = parse_policies(policies).unwrap();
+        let entities = Entities::from_json_str(entities_str, None).unwrap();
+        for ok in ["true", "false"] {
+            let request_str = REQUEST.replace("{}", &format!("{{ \"ok\": {ok} }}"));
+            let request = parse_request(&request_str, None).unwrap();
+            let response = Authorizer::new().is_authorized(&request, &pset, &entities);
+            let mut reasons: Vec<String> = response
+                .diagnostics()
+                .reason()
+                .map(ToString::to_string)
+                .collect();
+            reasons.sort();
+
+            // the reasons are the satisfied policies whose effect decided the request
+            let effect = match response.decision() {
+                Decision::Allow => "permit",
+                Decision::Deny => "forbid",
+            };
+            let result = explain_authorization_no_schema(policies, entities_str, &request_str);
+            let mut satisfied: Vec<String> = result
+                .policies
+                .unwrap()
+                .into_iter()
+                .filter(|trace| trace.status == "satisfied" && trace.effect == effect)
+                .map(|trace| trace.cedar_id)
+                .collect();
+            satisfied.sort();
+            assert!(!reasons.is_empty());
+            assert_eq!(satisfied, reasons);
+        }
+    }
+}
@@ -1,16 +1,39 @@
 // Copyright Cedar Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use cedar_policy_core::parser::text_to_cst;
 use cedar_policy_formatter::{policies_str_to_pretty, Config};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+use crate::schema_format::schema_str_to_pretty;
+use crate::text_edits::{diff_edits, TextEdit};
+use crate::utils::to_js_value;
+
 #[wasm_bindgen(typescript_custom_section)]
 const FORMAT_POLICIES: &'static str = r#"
 export class FormatPoliciesResult {
   free(): void;
   readonly success: boolean;
   readonly policy?: string;
+  readonly edits: Array<TextEdit> | undefined;
+  readonly error?: string;
+}
+export class FormatSchemaResult {
+  free(): void;
+  readonly success: boolean;
+  readonly schema?: string;
+  readonly edits: Array<TextEdit> | undefined;
+  readonly error?: string;
+}
+export class TextEdit {
+  readonly range: SourceRange;
+  readonly newText: string;
+}
+export class FormatPolicyRangeResult {
+  free(): void;
+  readonly success: boolean;
+  readonly edits: Array<TextEdit> | undefined;
   readonly error?: string;
 }"#;
 
@@ -19,9 +42,26 @@ export class FormatPoliciesResult {
 pub struct FormatPoliciesResult {
     pub success: bool,
     pub policy: Option<String>,
+    edits: Option<Vec<TextEdit>>,
     pub error: Option<String>,
 }
 
+#[wasm_bindgen]
+impl FormatPoliciesResult {
+    /// Minimal edits turning the input into `policy`
+    #[wasm_bindgen(getter)]
+    pub fn edits(&self) -> JsValue {
+        self.edits.as_ref().map_or(JsValue::UNDEFINED, to_js_value)
+    }
+}
+
+#[cfg(feature = "lsp")]
+impl FormatPoliciesResult {
+    pub(crate) fn into_edits(self) -> Option<Vec<TextEdit>> {
+        self.edits
+    }
+}
+
 #[wasm_bindgen(js_name = formatPolicies)]
 pub fn format_policies(
     policies_str: &str,
@@ -29,23 +69,167 @@ pub fn format_policies(
     indent_width: isize,
 ) -> FormatPoliciesResult {
     let config = Config {
-        line_width: line_width,
-        indent_width: indent_width,
+        line_width,
+        indent_width,
     };
     match policies_str_to_pretty(policies_str, &config) {
         Ok(prettified_policy) => FormatPoliciesResult {
             success: true,
+            edits: Some(diff_edits(
+                policies_str,
+                0,
+                policies_str.len(),
+                &prettified_policy,
+            )),
             policy: Some(prettified_policy),
             error: None,
         },
         Err(err) => FormatPoliciesResult {
             success: false,
             policy: None,
+            edits: None,
             error: Some(String::from(&format!("Format error: {err}"))),
         },
     }
 }
 
+#[wasm_bindgen(getter_with_clone, skip_typescript)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FormatSchemaResult {
+    pub success: bool,
+    pub schema: Option<String>,
+    edits: Option<Vec<TextEdit>>,
+    pub error: Option<String>,
+}
+
+#[wasm_bindgen]
+impl FormatSchemaResult {
+    /// Minimal edits turning the input into `schema`
+    #[wasm_bindgen(getter)]
+    pub fn edits(&self) -> JsValue {
+        self.edits.as_ref().map_or(JsValue::UNDEFINED, to_js_value)
+    }
+}
+
+#[cfg(feature = "lsp")]
+impl FormatSchemaResult {
+    pub(crate) fn into_edits(self) -> Option<Vec<TextEdit>> {
+        self.edits
+    }
+}
+
+#[wasm_bindgen(js_name = formatSchema)]
+pub fn format_schema(
+    schema_str: &str,
+    line_width: usize,
+    indent_width: isize,
+) -> FormatSchemaResult {
+    let config = Config {
+        line_width,
+        indent_width,
+    };
+    match schema_str_to_pretty(schema_str, &config) {
+        Ok(prettified_schema) => FormatSchemaResult {
+            success: true,
+            edits: Some(diff_edits(
+                schema_str,
+                0,
+                schema_str.len(),
+                &prettified_schema,
+            )),
+            schema: Some(prettified_schema),
+            error: None,
+        },
+        Err(err) => FormatSchemaResult {
+            success: false,
+            schema: None,
+            edits: None,
+            error: Some(format!("Format error: {err}")),
+        },
+    }
+}
+
+#[wasm_bindgen(getter_with_clone, skip_typescript)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FormatPolicyRangeResult {
+    pub success: bool,
+    edits: Option<Vec<TextEdit>>,
+    pub error: Option<String>,
+}
+
+#[wasm_bindgen]
+impl FormatPolicyRangeResult {
+    #[wasm_bindgen(getter)]
+    pub fn edits(&self) -> JsValue {
+        self.edits.as_ref().map_or(JsValue::UNDEFINED, to_js_value)
+    }
+}
+
+#[cfg(feature = "lsp")]
+impl FormatPolicyRangeResult {
+    pub(crate) fn into_edits(self) -> Option<Vec<TextEdit>> {
+        self.edits
+    }
+}
+
+/// Minimal edits formatting each policy in `policies_str` that overlaps the
+/// byte span `start_offset..end_offset`, where a policy touching either end
+/// counts so that an empty range formats the policy under the cursor
+fn policy_range_edits(
+    policies_str: &str,
+    start_offset: usize,
+    end_offset: usize,
+    config: &Config,
+) -> Result<Vec<TextEdit>, String> {
+    let cst = text_to_cst::parse_policies(policies_str)
+        .map_err(|err| format!("cannot parse input policies: {err}"))?;
+    let Some(policies) = cst.as_inner() else {
+        return Err(String::from("cannot parse input policies"));
+    };
+    let mut edits = Vec::new();
+    for loc in policies.0.iter().filter_map(|node| node.loc.as_ref()) {
+        if loc.start() > end_offset || loc.end() < start_offset {
+            continue;
+        }
+        let policy_str = &policies_str[loc.start()..loc.end()];
+        let formatted =
+            policies_str_to_pretty(policy_str, config).map_err(|err| err.to_string())?;
+        edits.extend(diff_edits(
+            policies_str,
+            loc.start(),
+            loc.end(),
+            formatted.trim_end(),
+        ));
+    }
+    Ok(edits)
+}
+
+#[wasm_bindgen(js_name = formatPolicyRange)]
+pub fn format_policy_range(
+    policies_str: &str,
+    start_offset: usize,
+    end_offset: usize,
+    line_width: usize,
+    indent_width: isize,
+) -> FormatPolicyRangeResult {
+    let config = Config {
+        line_width,
+        indent_width,
+    };
+    match policy_range_edits(policies_str, start_offset, end_offset, &config) {
+        Ok(edits) => FormatPolicyRangeResult {
+            success: true,
+            edits: Some(edits),
+            error: None,
+        },
+        Err(err) => FormatPolicyRangeResult {
+            success: false,
+            edits: None,
+            error: Some(format!("Format error: {err}")),
+        },
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -59,4 +243,29 @@ mod test {
             Some(expected.to_string())
         );
     }
+
+    #[test]
+    fn test_format_policy_range() {
+        let policies = "permit(principal,action,resource);\n\n// keep\nforbid(principal,action,resource) unless {principal.level>3};\npermit(principal,action,resource);\n";
+        let forbid_offset = policies.find("forbid").unwrap();
+        let result = format_policy_range(policies, forbid_offset + 3, forbid_offset + 3, 80, 2);
+        assert!(result.success);
+        let edits = result.edits.unwrap();
+        assert!(edits.iter().all(|edit| edit.range.start_line == 3));
+        let mut formatted = policies.to_string();
+        for edit in edits.iter().rev() {
+            formatted.replace_range(
+                edit.range.offset..edit.range.offset + edit.range.length,
+                &edit.new_text,
+            );
+        }
+        assert_eq!(
+            formatted,
+            "permit(principal,action,resource);\n\n// keep\nforbid (principal, action, resource)\nunless { principal.level > 3 };\npermit(principal,action,resource);\n"
+        );
+
+        let all = format_policy_range(policies, 0, policies.len(), 80, 2);
+        assert!(all.edits.unwrap().len() > edits.len());
+        assert!(!format_policy_range("permit(", 0, 0, 80, 2).success);
+    }
 }
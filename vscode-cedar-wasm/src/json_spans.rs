@@ -0,0 +1,228 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// serde_json does not keep source locations, so this module scans JSON text a
+// second time to find the byte span of each value for positioning messages
+
+/// Byte span of a JSON value along with the spans of its elements or members
+#[derive(Debug, Clone)]
+pub struct JsonNode {
+    pub offset: usize,
+    pub length: usize,
+    value: JsonValue,
+}
+
+#[derive(Debug, Clone)]
+enum JsonValue {
+    Scalar,
+    Array(Vec<JsonNode>),
+    Object(Vec<(String, JsonNode)>),
+}
+
+impl JsonNode {
+    /// Scan `src`, returning `None` when it is not well-formed JSON
+    pub fn parse(src: &str) -> Option<JsonNode> {
+        let mut scanner = Scanner {
+            src: src.as_bytes(),
+            pos: 0,
+        };
+        let node = scanner.value()?;
+        scanner.whitespace();
+        if scanner.pos == scanner.src.len() {
+            Some(node)
+        } else {
+            None
+        }
+    }
+
+    /// Value of the member `key` when this node is an object
+    pub fn get(&self, key: &str) -> Option<&JsonNode> {
+        match &self.value {
+            JsonValue::Object(members) => members
+                .iter()
+                .rev()
+                .find(|(k, _)| k == key)
+                .map(|(_, node)| node),
+            _ => None,
+        }
+    }
+
+    /// Elements of this node when it is an array
+    pub fn elements(&self) -> &[JsonNode] {
+        match &self.value {
+            JsonValue::Array(elements) => elements,
+            _ => &[],
+        }
+    }
+
+    /// Members of this node when it is an object
+    pub fn members(&self) -> &[(String, JsonNode)] {
+        match &self.value {
+            JsonValue::Object(members) => members,
+            _ => &[],
+        }
+    }
+
+    /// Value of this node in `src` when it is a string
+    pub fn string(&self, src: &str) -> Option<String> {
+        serde_json::from_str(src.get(self.offset..self.offset + self.length)?).ok()
+    }
+
+    /// Follow `path` of object keys, returning the deepest node found
+    pub fn find<'a>(&'a self, path: &[&str]) -> &'a JsonNode {
+        path.iter()
+            .try_fold(self, |node, key| node.get(key).ok_or(node))
+            .unwrap_or_else(|node| node)
+    }
+}
+
+/// Byte offset in `src` of the line and column reported by a `serde_json`
+/// error, which are one-based with the column counted in bytes
+pub fn serde_error_offset(src: &str, e: &serde_json::Error) -> usize {
+    let line_start = src
+        .split_inclusive('\n')
+        .take(e.line().saturating_sub(1))
+        .map(str::len)
+        .sum::<usize>();
+    (line_start + e.column().saturating_sub(1)).min(src.len())
+}
+
+struct Scanner<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.src.get(self.pos) {
+            self.pos += 1;
+        }
+    }
+
+    fn node(&self, start: usize, value: JsonValue) -> JsonNode {
+        JsonNode {
+            offset: start,
+            length: self.pos - start,
+            value,
+        }
+    }
+
+    fn value(&mut self) -> Option<JsonNode> {
+        self.whitespace();
+        let start = self.pos;
+        match self.src.get(self.pos)? {
+            b'{' => {
+                self.pos += 1;
+                let mut members = Vec::new();
+                self.whitespace();
+                if self.src.get(self.pos) == Some(&b'}') {
+                    self.pos += 1;
+                    return Some(self.node(start, JsonValue::Object(members)));
+                }
+                loop {
+                    self.whitespace();
+                    let key_start = self.pos;
+                    self.string()?;
+                    let key = serde_json::from_slice(&self.src[key_start..self.pos]).ok()?;
+                    self.whitespace();
+                    self.expect(b':')?;
+                    members.push((key, self.value()?));
+                    self.whitespace();
+                    match self.src.get(self.pos)? {
+                        b',' => self.pos += 1,
+                        b'}' => {
+                            self.pos += 1;
+                            return Some(self.node(start, JsonValue::Object(members)));
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut elements = Vec::new();
+                self.whitespace();
+                if self.src.get(self.pos) == Some(&b']') {
+                    self.pos += 1;
+                    return Some(self.node(start, JsonValue::Array(elements)));
+                }
+                loop {
+                    elements.push(self.value()?);
+                    self.whitespace();
+                    match self.src.get(self.pos)? {
+                        b',' => self.pos += 1,
+                        b']' => {
+                            self.pos += 1;
+                            return Some(self.node(start, JsonValue::Array(elements)));
+                        }
+                        _ => return None,
+                    }
+                }
+            }
+            b'"' => {
+                self.string()?;
+                Some(self.node(start, JsonValue::Scalar))
+            }
+            _ => {
+                while let Some(b) = self.src.get(self.pos) {
+                    if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.') {
+                        self.pos += 1;
+                    } else {
+                        break;
+                    }
+                }
+                if self.pos == start {
+                    None
+                } else {
+                    Some(self.node(start, JsonValue::Scalar))
+                }
+            }
+        }
+    }
+
+    fn string(&mut self) -> Option<()> {
+        self.expect(b'"')?;
+        loop {
+            match self.src.get(self.pos)? {
+                b'"' => {
+                    self.pos += 1;
+                    return Some(());
+                }
+                b'\\' => self.pos += 2,
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    fn expect(&mut self, b: u8) -> Option<()> {
+        if self.src.get(self.pos) == Some(&b) {
+            self.pos += 1;
+            Some(())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn json_spans_locate_nested_values() {
+        let src = r#"[ { "uid": { "type": "User", "id": "a\"b" }, "attrs": { "n": -1.5e3 } } ]"#;
+        let root = JsonNode::parse(src).unwrap();
+        let entity = &root.elements()[0];
+        let id = entity.find(&["uid", "id"]);
+        assert_eq!(&src[id.offset..id.offset + id.length], r#""a\"b""#);
+        let n = entity.find(&["attrs", "n"]);
+        assert_eq!(&src[n.offset..n.offset + n.length], "-1.5e3");
+        let missing = entity.find(&["attrs", "missing"]);
+        assert_eq!(&src[missing.offset..missing.offset + 1], "{");
+        assert!(JsonNode::parse("[1, 2").is_none());
+
+        let src = "{\n  \"a\": 1 \"b\": 2\n}";
+        let e = serde_json::from_str::<serde_json::Value>(src).unwrap_err();
+        assert_eq!(&src[serde_error_offset(src, &e)..][..3], "\"b\"");
+    }
+}
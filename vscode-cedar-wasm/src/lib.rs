@@ -1,12 +1,32 @@
 // Copyright Cedar Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+mod access_matrix;
+mod authorizer;
+#[cfg(any(feature = "cli", feature = "lsp"))]
+mod cedar_files;
+#[cfg(feature = "cli")]
+pub mod cli;
 mod entities_validator;
+mod entity_checker;
+mod explain;
 mod format;
+mod json_spans;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+mod partial_authorizer;
 mod policy;
+mod policy_diff;
+mod policy_outline;
+mod policy_tests;
 mod policy_validator;
+mod schema_format;
+mod schema_introspect;
 mod schema_translate;
 mod schema_validator;
 mod syntax_validator;
+mod template_links;
+mod text_edits;
 mod utils;
-mod validate_message;
\ No newline at end of file
+mod validate_message;
+mod workspace;
\ No newline at end of file
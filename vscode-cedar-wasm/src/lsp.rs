@@ -0,0 +1,671 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Native entry point for the `cedar-language-server` binary, which speaks the
+// Language Server Protocol over stdio so editors other than VS Code get the
+// same diagnostics, formatting and document symbols as the extension
+
+use cedar_policy::{Schema, ValidationMode, Validator};
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::cedar_files::{file_kind, schema_file, FileKind};
+use crate::entities_validator::parse_entities;
+use crate::format::{format_policies, format_policy_range, format_schema};
+use crate::policy_outline::parse_policy_outline;
+use crate::policy_validator::{parse_validation_mode, validate_parsed_policies};
+use crate::schema_validator::{parse_schema_cedar, parse_schema_json, ValidateSchemaResult};
+use crate::syntax_validator::parse_policies;
+use crate::text_edits::TextEdit;
+use crate::validate_message::{SourceRange, ValidateMessage};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+
+const SYMBOL_FUNCTION: u8 = 12;
+const SYMBOL_VARIABLE: u8 = 13;
+const SYMBOL_BOOLEAN: u8 = 17;
+
+/// Formatting options carry no line width, so use VS Code's default
+/// `editor.wordWrapColumn`
+const LINE_WIDTH: usize = 80;
+
+/// Read the body of one message framed with a `Content-Length` header, or
+/// `None` at the end of the input
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(length) = length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing Content-Length header",
+        ));
+    };
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()
+}
+
+fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// Path of a `file:` URI, decoding percent escapes
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let escaped = (encoded[i] == b'%')
+            .then(|| encoded.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(encoded[i]);
+                i += 1;
+            }
+        }
+    }
+    let path = String::from_utf8(decoded).ok()?;
+    // `file:///c:/...` on Windows
+    if cfg!(windows) && path.get(2..3) == Some(":") {
+        return Some(PathBuf::from(&path[1..]));
+    }
+    Some(PathBuf::from(path))
+}
+
+/// Byte offset in `src` of an LSP position, counting characters in UTF-16
+/// code units and clamping positions past the end of a line
+fn byte_offset(src: &str, position: &Value) -> usize {
+    let line = position["line"].as_u64().unwrap_or_default() as usize;
+    let character = position["character"].as_u64().unwrap_or_default() as usize;
+    let line_start = match line {
+        0 => 0,
+        _ => src
+            .match_indices('\n')
+            .nth(line - 1)
+            .map_or(src.len(), |(i, _)| i + 1),
+    };
+    let mut units = 0;
+    for (i, c) in src[line_start..].char_indices() {
+        if units >= character || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    src.len()
+}
+
+fn range(
+    start_line: usize,
+    start_character: usize,
+    end_line: usize,
+    end_character: usize,
+) -> Value {
+    json!({
+        "start": { "line": start_line, "character": start_character },
+        "end": { "line": end_line, "character": end_character },
+    })
+}
+
+fn source_range(source: &SourceRange) -> Value {
+    range(
+        source.start_line,
+        source.start_character,
+        source.end_line,
+        source.end_character,
+    )
+}
+
+fn diagnostic(message: &ValidateMessage, severity: u8, code: &str) -> Value {
+    json!({
+        "range": range(
+            message.start_line,
+            message.start_character,
+            message.end_line,
+            message.end_character,
+        ),
+        "severity": severity,
+        "code": code,
+        "source": "cedar",
+        "message": message.message,
+    })
+}
+
+fn text_edit(edit: &TextEdit) -> Value {
+    json!({ "range": source_range(&edit.range), "newText": edit.new_text })
+}
+
+struct Document {
+    path: Option<PathBuf>,
+    kind: FileKind,
+    text: String,
+}
+
+/// Kind of an open document without a Cedar file name, such as an untitled
+/// one, from the language ID the extension registers
+fn language_kind(language_id: &str) -> Option<FileKind> {
+    match language_id {
+        "cedar" => Some(FileKind::Policies),
+        "cedarschema" => Some(FileKind::Schema),
+        _ => None,
+    }
+}
+
+/// A schema parsed from `text`, the open document or the file on disk
+struct CachedSchema {
+    text: String,
+    schema: Option<Schema>,
+}
+
+struct Server {
+    root: Option<PathBuf>,
+    mode: ValidationMode,
+    documents: BTreeMap<String, Document>,
+    /// Schemas by path, kept until the file is saved, closed or changes on
+    /// disk
+    schemas: RefCell<BTreeMap<PathBuf, CachedSchema>>,
+    initialized: bool,
+    shutdown: bool,
+}
+
+impl Server {
+    fn new() -> Self {
+        Server {
+            root: None,
+            mode: ValidationMode::Strict,
+            documents: BTreeMap::new(),
+            schemas: RefCell::new(BTreeMap::new()),
+            initialized: false,
+            shutdown: false,
+        }
+    }
+
+    /// Responses and notifications to send for `message`
+    fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            if !self.initialized {
+                // notifications before `initialize` are dropped
+                return Vec::new();
+            }
+            return self.notification(method, params);
+        };
+        if message.get("method").is_none() {
+            // a response, but this server sends no requests
+            return Vec::new();
+        }
+        let result = if !self.initialized && method != "initialize" {
+            Err((
+                SERVER_NOT_INITIALIZED,
+                String::from("server has not been initialized"),
+            ))
+        } else if self.shutdown {
+            Err((INVALID_REQUEST, String::from("server is shutting down")))
+        } else {
+            self.request(method, params)
+        };
+        vec![match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        }]
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        match method {
+            "initialize" => self.initialize(params),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/formatting" => self.format(params, None),
+            "textDocument/rangeFormatting" => self.format(params, Some(&params["range"])),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method `{method}`"))),
+        }
+    }
+
+    fn initialize(&mut self, params: &Value) -> Result<Value, (i64, String)> {
+        self.root = params["rootUri"]
+            .as_str()
+            .or_else(|| params["workspaceFolders"][0]["uri"].as_str())
+            .and_then(uri_to_path)
+            .or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+        let mode = params["initializationOptions"]["validationMode"]
+            .as_str()
+            .map(String::from);
+        self.mode = parse_validation_mode(mode).map_err(|e| (INVALID_PARAMS, e))?;
+        self.initialized = true;
+        Ok(json!({
+            "capabilities": {
+                "textDocumentSync": { "openClose": true, "change": 1, "save": true },
+                "documentFormattingProvider": true,
+                "documentRangeFormattingProvider": true,
+                "documentSymbolProvider": true,
+            },
+            "serverInfo": {
+                "name": "cedar-language-server",
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        }))
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let uri = params["textDocument"]["uri"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        match method {
+            "textDocument/didOpen" => {
+                let path = uri_to_path(&uri);
+                let kind = path
+                    .as_deref()
+                    .and_then(Path::file_name)
+                    .and_then(|name| file_kind(&name.to_string_lossy()))
+                    .or_else(|| language_kind(params["textDocument"]["languageId"].as_str()?));
+                let Some(kind) = kind else {
+                    return Vec::new();
+                };
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(
+                    uri.clone(),
+                    Document {
+                        path,
+                        kind,
+                        text: text.to_string(),
+                    },
+                );
+                self.publish_changed(&uri)
+            }
+            "textDocument/didChange" => {
+                // documents are synced in full, so the last change holds the
+                // whole text
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                let (Some(text), Some(document)) = (text, self.documents.get_mut(&uri)) else {
+                    return Vec::new();
+                };
+                document.text = text.to_string();
+                self.publish_changed(&uri)
+            }
+            "textDocument/didClose" => {
+                let Some(document) = self.documents.remove(&uri) else {
+                    return Vec::new();
+                };
+                let mut messages = vec![publish(&uri, Vec::new())];
+                if matches!(document.kind, FileKind::Schema | FileKind::SchemaJson) {
+                    // the file on disk may differ from the closed document
+                    if let Some(path) = uri_to_path(&uri) {
+                        self.forget_schema(&path);
+                    }
+                    messages.extend(self.publish_all());
+                }
+                messages
+            }
+            "textDocument/didSave" => {
+                if let Some(path) = uri_to_path(&uri) {
+                    self.forget_schema(&path);
+                }
+                Vec::new()
+            }
+            "workspace/didChangeWatchedFiles" => {
+                let changes = params["changes"].as_array().cloned().unwrap_or_default();
+                let mut schema_changed = false;
+                for change in changes {
+                    let Some(path) = change["uri"].as_str().and_then(uri_to_path) else {
+                        continue;
+                    };
+                    self.forget_schema(&path);
+                    schema_changed |= path
+                        .file_name()
+                        .and_then(|name| file_kind(&name.to_string_lossy()))
+                        .is_some_and(|kind| {
+                            matches!(kind, FileKind::Schema | FileKind::SchemaJson)
+                        });
+                }
+                // documents validated against the schema on disk
+                if schema_changed {
+                    self.publish_all()
+                } else {
+                    Vec::new()
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Diagnostics for the document at `uri`, or for every open document when
+    /// it is a schema other documents may be validated against
+    fn publish_changed(&self, uri: &str) -> Vec<Value> {
+        match self.documents[uri].kind {
+            FileKind::Schema | FileKind::SchemaJson => self.publish_all(),
+            _ => vec![publish(uri, self.diagnostics(&self.documents[uri]))],
+        }
+    }
+
+    fn publish_all(&self) -> Vec<Value> {
+        self.documents
+            .iter()
+            .map(|(uri, document)| publish(uri, self.diagnostics(document)))
+            .collect()
+    }
+
+    fn diagnostics(&self, document: &Document) -> Vec<Value> {
+        let text = &document.text;
+        match document.kind {
+            FileKind::Policies => {
+                let parse_result = parse_policies(text);
+                if let Err(errors) = &parse_result {
+                    return errors
+                        .iter()
+                        .map(|error| diagnostic(error, SEVERITY_ERROR, "syntax"))
+                        .collect();
+                }
+                let Some(schema) = self.schema_for(document) else {
+                    return Vec::new();
+                };
+                let result = validate_parsed_policies(
+                    &Validator::new(schema),
+                    &parse_result,
+                    text,
+                    self.mode,
+                );
+                let mut diagnostics = Vec::new();
+                for policy_diagnostic in result.policy_diagnostics() {
+                    let severity = match policy_diagnostic.severity.as_str() {
+                        "warning" => SEVERITY_WARNING,
+                        _ => SEVERITY_ERROR,
+                    };
+                    let unlocated = [ValidateMessage::without_span(
+                        policy_diagnostic.message.clone(),
+                    )];
+                    let labels = match policy_diagnostic.labels.as_slice() {
+                        [] => &unlocated,
+                        labels => labels,
+                    };
+                    diagnostics.extend(
+                        labels
+                            .iter()
+                            .map(|label| diagnostic(label, severity, &policy_diagnostic.kind)),
+                    );
+                }
+                diagnostics
+            }
+            FileKind::Schema => schema_diagnostics(parse_schema_cedar(text).0),
+            FileKind::SchemaJson => schema_diagnostics(parse_schema_json(text).0),
+            FileKind::Entities => match parse_entities(text, self.schema_for(document).as_ref()) {
+                Ok(_) => Vec::new(),
+                Err(errors) => errors
+                    .iter()
+                    .map(|error| diagnostic(&error.to_message(), SEVERITY_ERROR, "entities"))
+                    .collect(),
+            },
+            FileKind::TemplateLinks | FileKind::Request => Vec::new(),
+        }
+    }
+
+    /// The schema `document` is validated against: the single schema in its
+    /// directory or, failing that, in the workspace root, read from the open
+    /// document when there is one rather than from disk
+    fn schema_for(&self, document: &Document) -> Option<Schema> {
+        let dir = document.path.as_deref()?.parent()?;
+        let path = self
+            .schema_path(dir)
+            .or_else(|| self.schema_path(self.root.as_deref()?))?;
+        let open = self
+            .documents
+            .values()
+            .find(|document| document.path.as_deref() == Some(path.as_path()));
+        let mut schemas = self.schemas.borrow_mut();
+        if let Some(cached) = schemas.get(&path) {
+            if open.is_none_or(|document| document.text == cached.text) {
+                return cached.schema.clone();
+            }
+        }
+        let (text, kind) = match open {
+            Some(document) => (document.text.clone(), document.kind),
+            None => (
+                fs::read_to_string(&path).ok()?,
+                file_kind(&path.file_name()?.to_string_lossy())?,
+            ),
+        };
+        let schema = match kind {
+            FileKind::SchemaJson => parse_schema_json(&text).1,
+            _ => parse_schema_cedar(&text).1,
+        };
+        schemas.insert(
+            path,
+            CachedSchema {
+                text,
+                schema: schema.clone(),
+            },
+        );
+        schema
+    }
+
+    /// Drop the schema parsed from `path`, so it is read again when needed
+    fn forget_schema(&self, path: &Path) {
+        self.schemas.borrow_mut().remove(path);
+    }
+
+    /// The schema file in `dir`, counting open documents not yet saved
+    fn schema_path(&self, dir: &Path) -> Option<PathBuf> {
+        let mut files: Vec<(PathBuf, FileKind)> = fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                let kind = file_kind(&path.file_name()?.to_string_lossy())?;
+                Some((path, kind))
+            })
+            .collect();
+        for document in self.documents.values() {
+            if let Some(path) = &document.path {
+                if path.parent() == Some(dir) && !files.iter().any(|(file, _)| file == path) {
+                    files.push((path.clone(), document.kind));
+                }
+            }
+        }
+        schema_file(&files).map(Path::to_path_buf)
+    }
+
+    fn document(&self, params: &Value) -> Result<&Document, (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        self.documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("document `{uri}` is not open")))
+    }
+
+    /// Edits formatting the document, or the policies overlapping `range`,
+    /// or `null` when it cannot be formatted
+    fn format(&self, params: &Value, range: Option<&Value>) -> Result<Value, (i64, String)> {
+        let document = self.document(params)?;
+        let text = &document.text;
+        let indent_width = params["options"]["tabSize"].as_i64().unwrap_or(2) as isize;
+        let edits = match (document.kind, range) {
+            (FileKind::Policies, None) => {
+                format_policies(text, LINE_WIDTH, indent_width).into_edits()
+            }
+            (FileKind::Policies, Some(range)) => format_policy_range(
+                text,
+                byte_offset(text, &range["start"]),
+                byte_offset(text, &range["end"]),
+                LINE_WIDTH,
+                indent_width,
+            )
+            .into_edits(),
+            (FileKind::Schema, None) => format_schema(text, LINE_WIDTH, indent_width).into_edits(),
+            _ => None,
+        };
+        Ok(edits.map_or(Value::Null, |edits| edits.iter().map(text_edit).collect()))
+    }
+
+    /// The outline of a policies document, with the same symbols as the
+    /// extension's outline view
+    fn document_symbols(&self, params: &Value) -> Result<Value, (i64, String)> {
+        let document = self.document(params)?;
+        if document.kind != FileKind::Policies {
+            return Ok(json!([]));
+        }
+        let policies = parse_policy_outline(&document.text)
+            .into_policies()
+            .unwrap_or_default();
+        Ok(policies
+            .iter()
+            .map(|policy| {
+                let scope = policy.scope.iter().map(|scope| {
+                    json!({
+                        "name": scope.constraint,
+                        "kind": SYMBOL_VARIABLE,
+                        "range": source_range(&scope.range),
+                        "selectionRange": source_range(&scope.range),
+                    })
+                });
+                let conditions = policy.conditions.iter().map(|condition| {
+                    json!({
+                        "name": condition.kind,
+                        "kind": SYMBOL_BOOLEAN,
+                        "range": source_range(&condition.range),
+                        "selectionRange": source_range(&condition.range),
+                    })
+                });
+                json!({
+                    "name": policy.policy_id,
+                    "detail": if policy.kind == "template" { "template" } else { "" },
+                    "kind": SYMBOL_FUNCTION,
+                    "range": source_range(&policy.range),
+                    "selectionRange": source_range(&policy.effect_range),
+                    "children": scope.chain(conditions).collect::<Vec<_>>(),
+                })
+            })
+            .collect())
+    }
+}
+
+fn schema_diagnostics(result: ValidateSchemaResult) -> Vec<Value> {
+    let mut diagnostics: Vec<Value> = result
+        .warning_messages()
+        .iter()
+        .map(|warning| diagnostic(warning, SEVERITY_WARNING, "schema"))
+        .collect();
+    diagnostics.extend(
+        result
+            .into_errors()
+            .unwrap_or_default()
+            .iter()
+            .map(|error| diagnostic(error, SEVERITY_ERROR, "schema")),
+    );
+    diagnostics
+}
+
+fn publish(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+/// Serve requests on stdin until the client sends `exit`, exiting with 0
+/// only when it sent `shutdown` first as the protocol requires
+pub fn main() -> ExitCode {
+    let mut reader = io::stdin().lock();
+    let mut writer = io::stdout().lock();
+    let mut server = Server::new();
+    loop {
+        let body = match read_message(&mut reader) {
+            Ok(Some(body)) => body,
+            Ok(None) => return ExitCode::FAILURE,
+            Err(e) => {
+                eprintln!("error: cannot read message: {e}");
+                return ExitCode::FAILURE;
+            }
+        };
+        let responses = match serde_json::from_str::<Value>(&body) {
+            Ok(message) if message["method"] == "exit" => {
+                return if server.shutdown {
+                    ExitCode::SUCCESS
+                } else {
+                    ExitCode::FAILURE
+                };
+            }
+            Ok(message) => server.handle(&message),
+            Err(e) => vec![error_response(&Value::Null, PARSE_ERROR, &e.to_string())],
+        };
+        for response in responses {
+            if let Err(e) = write_message(&mut writer, &response) {
+                eprintln!("error: cannot write message: {e}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn positions_convert_to_byte_offsets() {
+        let src = "// é😀\npermit(principal, action, resource);\n";
+        assert_eq!(byte_offset(src, &json!({ "line": 0, "character": 4 })), 5);
+        assert_eq!(byte_offset(src, &json!({ "line": 0, "character": 6 })), 9);
+        assert_eq!(byte_offset(src, &json!({ "line": 0, "character": 99 })), 9);
+        assert_eq!(byte_offset(src, &json!({ "line": 1, "character": 6 })), 16);
+        assert_eq!(
+            byte_offset(src, &json!({ "line": 5, "character": 0 })),
+            src.len()
+        );
+        assert_eq!(
+            uri_to_path("file:///work/my%20policies/a.cedar"),
+            Some(PathBuf::from("/work/my policies/a.cedar"))
+        );
+        assert_eq!(uri_to_path("untitled:Untitled-1"), None);
+    }
+}
@@ -0,0 +1,344 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use cedar_policy::{Authorizer, Context, EntityTypeName, Request, RequestBuilder, Schema};
+use cedar_policy_formatter::{policies_str_to_pretty, Config};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+use crate::authorizer::{decision_name, field_message, parse_entity_uid, request_error_field};
+use crate::entities_validator::parse_entities_messages;
+use crate::json_spans::{serde_error_offset, JsonNode};
+use crate::syntax_validator::parse_policies;
+use crate::validate_message::{convert_messages_to_js_array, ValidateMessage};
+
+#[wasm_bindgen(typescript_custom_section)]
+const PARTIAL_AUTHORIZATION_RESULT: &'static str = r#"
+export class PartialAuthorizationResult {
+  free(): void;
+  readonly success: boolean;
+  readonly decision: "allow" | "deny" | undefined;
+  readonly residuals: string | undefined;
+  readonly errors: Array<ValidateMessage> | undefined;
+}"#;
+
+#[wasm_bindgen(getter_with_clone, skip_typescript)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PartialAuthorizationResult {
+    #[wasm_bindgen(readonly)]
+    pub success: bool,
+    #[wasm_bindgen(readonly)]
+    pub decision: Option<String>,
+    #[wasm_bindgen(readonly)]
+    pub residuals: Option<String>,
+    errors: Option<Vec<ValidateMessage>>,
+}
+
+#[wasm_bindgen]
+impl PartialAuthorizationResult {
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Option<js_sys::Array> {
+        self.errors.as_deref().map(convert_messages_to_js_array)
+    }
+}
+
+/// A `cedarauth.json` request where an omitted or `null` principal, action,
+/// resource or context is unknown. An unknown principal or resource may still
+/// have a known `principalType` or `resourceType`, and single context
+/// attributes are made unknown with `{ "__extn": { "fn": "unknown", "arg": "name" } }`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct PartialAuthorizationRequest {
+    principal: Option<String>,
+    principal_type: Option<String>,
+    action: Option<String>,
+    resource: Option<String>,
+    resource_type: Option<String>,
+    context: Option<serde_json::Value>,
+}
+
+fn create_error_result(errors: Vec<ValidateMessage>) -> PartialAuthorizationResult {
+    PartialAuthorizationResult {
+        success: false,
+        decision: None,
+        residuals: None,
+        errors: Some(errors),
+    }
+}
+
+/// Build a request with unknowns from `input_request_str`, validating the
+/// known parts against `schema` when one is provided
+fn parse_partial_request(
+    input_request_str: &str,
+    schema: Option<&Schema>,
+) -> Result<Request, Vec<ValidateMessage>> {
+    let request: PartialAuthorizationRequest =
+        serde_json::from_str(input_request_str).map_err(|e| {
+            vec![ValidateMessage::new(
+                format!("invalid authorization request: {e}"),
+                input_request_str,
+                serde_error_offset(input_request_str, &e),
+                0,
+            )]
+        })?;
+    let root = JsonNode::parse(input_request_str);
+    let at_field = |field: &str, e: &dyn std::fmt::Display| {
+        field_message(input_request_str, root.as_ref(), field, e)
+    };
+
+    let mut errors = Vec::new();
+    let mut builder = RequestBuilder::default();
+    match (&request.principal, &request.principal_type) {
+        (Some(uid), _) => match parse_entity_uid("principal", uid) {
+            Ok(uid) => builder = builder.principal(uid),
+            Err(e) => errors.push(at_field("principal", &e)),
+        },
+        (None, Some(type_name)) => match EntityTypeName::from_str(type_name) {
+            Ok(type_name) => builder = builder.unknown_principal_with_type(type_name),
+            Err(e) => errors.push(at_field("principalType", &e)),
+        },
+        (None, None) => {}
+    }
+    let mut action = None;
+    if let Some(uid) = &request.action {
+        match parse_entity_uid("action", uid) {
+            Ok(uid) => {
+                builder = builder.action(uid.clone());
+                action = Some(uid);
+            }
+            Err(e) => errors.push(at_field("action", &e)),
+        }
+    }
+    match (&request.resource, &request.resource_type) {
+        (Some(uid), _) => match parse_entity_uid("resource", uid) {
+            Ok(uid) => builder = builder.resource(uid),
+            Err(e) => errors.push(at_field("resource", &e)),
+        },
+        (None, Some(type_name)) => match EntityTypeName::from_str(type_name) {
+            Ok(type_name) => builder = builder.unknown_resource_with_type(type_name),
+            Err(e) => errors.push(at_field("resourceType", &e)),
+        },
+        (None, None) => {}
+    }
+    if let Some(context) = request.context {
+        // the schema can only type the context of a known action, otherwise
+        // it is checked along with the rest of the request below
+        let schema_action = schema.zip(action.as_ref());
+        match Context::from_json_value(context, schema_action) {
+            Ok(context) => builder = builder.context(context),
+            Err(e) => errors.push(at_field("context", &e)),
+        }
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    match schema {
+        Some(schema) => match builder.schema(schema).build() {
+            Ok(request) => Ok(request),
+            Err(e) => Err(vec![at_field(request_error_field(&e), &e)]),
+        },
+        None => Ok(builder.build()),
+    }
+}
+
+/// Cedar text of the policies that could not be reduced to `true` or
+/// `false`, formatted when the formatter accepts it
+fn residual_text(residuals: &[String], config: &Config) -> String {
+    if residuals.is_empty() {
+        return String::new();
+    }
+    let text = residuals.join("\n\n");
+    policies_str_to_pretty(&text, config).unwrap_or(text)
+}
+
+fn partial_authorize(
+    schema: Option<&Schema>,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_request_str: &str,
+    line_width: usize,
+    indent_width: isize,
+) -> PartialAuthorizationResult {
+    let pset = match parse_policies(input_policies_str) {
+        Ok(pset) => pset,
+        Err(errors) => return create_error_result(errors),
+    };
+    let entities = match parse_entities_messages(input_entities_str, schema) {
+        Ok(entities) => entities,
+        Err(errors) => return create_error_result(errors),
+    };
+    let request = match parse_partial_request(input_request_str, schema) {
+        Ok(request) => request,
+        Err(errors) => return create_error_result(errors),
+    };
+
+    let response = Authorizer::new().is_authorized_partial(&request, &pset, &entities);
+    let residuals: Vec<String> = response
+        .nontrivial_residuals()
+        .map(|policy| policy.to_string())
+        .collect();
+    let config = Config {
+        line_width,
+        indent_width,
+    };
+    PartialAuthorizationResult {
+        success: true,
+        decision: response
+            .decision()
+            .map(|decision| decision_name(decision).to_string()),
+        residuals: Some(residual_text(&residuals, &config)),
+        errors: None,
+    }
+}
+
+/// Partially evaluate the policies for a request with unknowns, returning
+/// the decision when the unknowns cannot change it and the residual policies
+#[wasm_bindgen(js_name = partialAuthorize)]
+pub fn partial_authorize_no_schema(
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_request_str: &str,
+    line_width: usize,
+    indent_width: isize,
+) -> PartialAuthorizationResult {
+    partial_authorize(
+        None,
+        input_policies_str,
+        input_entities_str,
+        input_request_str,
+        line_width,
+        indent_width,
+    )
+}
+
+#[wasm_bindgen(js_name = partialAuthorizeSchemaJSON)]
+pub fn partial_authorize_schema_json(
+    input_schema_str: &str,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_request_str: &str,
+    line_width: usize,
+    indent_width: isize,
+) -> PartialAuthorizationResult {
+    match Schema::from_json_str(input_schema_str) {
+        Ok(schema) => partial_authorize(
+            Some(&schema),
+            input_policies_str,
+            input_entities_str,
+            input_request_str,
+            line_width,
+            indent_width,
+        ),
+        Err(e) => create_error_result(vec![ValidateMessage::without_span(e.to_string())]),
+    }
+}
+
+#[wasm_bindgen(js_name = partialAuthorizeSchemaCedar)]
+pub fn partial_authorize_schema_cedar(
+    input_schema_str: &str,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_request_str: &str,
+    line_width: usize,
+    indent_width: isize,
+) -> PartialAuthorizationResult {
+    match Schema::from_cedarschema_str(input_schema_str) {
+        Ok((schema, _warnings)) => partial_authorize(
+            Some(&schema),
+            input_policies_str,
+            input_entities_str,
+            input_request_str,
+            line_width,
+            indent_width,
+        ),
+        Err(e) => create_error_result(vec![ValidateMessage::without_span(e.to_string())]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const POLICIES: &str = r#"@id("owners")
+permit (principal == User::"alice", action == Action::"view", resource)
+when { resource.owner == principal };
+
+@id("locked")
+forbid (principal, action, resource)
+when { context.locked };
+
+@id("bob")
+permit (principal == User::"bob", action, resource);
+"#;
+
+    const ENTITIES: &str = r#"[
+        { "uid": { "type": "User", "id": "alice" }, "attrs": {}, "parents": [] }
+    ]"#;
+
+    #[test]
+    fn partial_authorize_returns_residuals_for_unknowns() {
+        let request = r#"{
+            "principal": "User::\"alice\"",
+            "action": "Action::\"view\"",
+            "context": { "locked": false }
+        }"#;
+        let result = partial_authorize_no_schema(POLICIES, ENTITIES, request, 80, 2);
+        assert!(result.success, "{:?}", result.errors);
+        assert_eq!(result.decision, None);
+        let residuals = result.residuals.unwrap();
+        assert!(residuals.contains(r#"@id("owners")"#), "{residuals}");
+        assert!(residuals.contains(r#"unknown("resource")"#), "{residuals}");
+        assert!(!residuals.contains("locked"), "{residuals}");
+        assert!(!residuals.contains("bob"), "{residuals}");
+    }
+
+    #[test]
+    fn partial_authorize_reports_determined_decisions() {
+        let request = r#"{
+            "principal": "User::\"bob\"",
+            "action": "Action::\"view\"",
+            "resourceType": "Doc",
+            "context": { "locked": true }
+        }"#;
+        let result = partial_authorize_no_schema(POLICIES, ENTITIES, request, 80, 2);
+        assert_eq!(result.decision, Some(String::from("deny")));
+        assert_eq!(result.residuals, Some(String::new()));
+
+        let invalid = r#"{ "principal": "alice", "resourceType": "not a type" }"#;
+        let errors = partial_authorize_no_schema(POLICIES, ENTITIES, invalid, 80, 2)
+            .errors
+            .unwrap();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].start_character, 15);
+    }
+
+    #[test]
+    fn partial_authorize_types_context_with_schema() {
+        let schema = r#"entity User;
+entity Doc;
+action view appliesTo { principal: User, resource: Doc, context: { ip: ipaddr, owner: User } };
+"#;
+        let policies = r#"permit (principal, action, resource)
+when { context.ip.isLoopback() && context.owner == principal };"#;
+        let request = r#"{
+            "action": "Action::\"view\"",
+            "principalType": "User",
+            "resourceType": "Doc",
+            "context": { "ip": "127.0.0.1", "owner": { "type": "User", "id": "alice" } }
+        }"#;
+        let result = partial_authorize_schema_cedar(schema, policies, "[]", request, 80, 2);
+        assert!(result.success, "{:?}", result.errors);
+        assert_eq!(result.decision, None);
+        let residuals = result.residuals.unwrap();
+        assert!(residuals.contains(r#"User::"alice""#), "{residuals}");
+        assert!(!residuals.contains("isLoopback"), "{residuals}");
+
+        let entities =
+            r#"[{ "uid": { "type": "User", "id": "a" }, "attrs": { "name": 1 }, "parents": [] }]"#;
+        let errors = partial_authorize_schema_cedar(schema, policies, entities, request, 80, 2)
+            .errors
+            .unwrap();
+        assert_eq!(&entities[errors[0].offset..][..errors[0].length], "1");
+    }
+}
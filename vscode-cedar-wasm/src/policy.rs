@@ -3,16 +3,26 @@
 
 use std::str::FromStr;
 
-use cedar_policy::{Policy, PolicySet, Template};
+use cedar_policy::{Policy, PolicyId, PolicySet, PolicySetError, Template};
+use cedar_policy_formatter::{policies_str_to_pretty, Config};
+use miette::Diagnostic;
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+use crate::json_spans::serde_error_offset;
+use crate::validate_message::{
+    convert_messages_to_js_array, diagnostic_message, format_diagnostic, messages_from_diagnostic,
+    ValidateMessage,
+};
+
 #[wasm_bindgen(typescript_custom_section)]
 const EXPORT_POLICY_RESULT: &'static str = r#"
 export class ExportPolicyResult {
   free(): void;
   readonly success: boolean;
   readonly json: string | undefined;
+  readonly errorKind: "parse" | "serialize" | undefined;
+  readonly errors: Array<ValidateMessage> | undefined;
 }"#;
 
 #[wasm_bindgen(getter_with_clone, skip_typescript)]
@@ -21,70 +31,282 @@ pub struct ExportPolicyResult {
     #[wasm_bindgen(readonly)]
     pub success: bool,
     pub json: Option<String>,
+    #[wasm_bindgen(readonly, js_name = errorKind)]
+    pub error_kind: Option<String>,
+    errors: Option<Vec<ValidateMessage>>,
 }
 
-#[wasm_bindgen(js_name = exportPolicy)]
-pub fn export_policy(input_policy_str: &str) -> ExportPolicyResult {
-    let Ok(policy) = Policy::parse(None, input_policy_str) else {
-        return ExportPolicyResult {
-            success: false,
-            json: None,
-        };
-    };
+#[wasm_bindgen]
+impl ExportPolicyResult {
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Option<js_sys::Array> {
+        self.errors.as_deref().map(convert_messages_to_js_array)
+    }
+}
 
-    let Ok(json) = policy.to_json() else {
-        return ExportPolicyResult {
-            success: false,
-            json: None,
-        };
-    };
+/// Result for text that could not be parsed, with errors located in `src`
+fn export_parse_error(src: &str, e: &dyn Diagnostic) -> ExportPolicyResult {
+    ExportPolicyResult {
+        success: false,
+        json: None,
+        error_kind: Some(String::from("parse")),
+        errors: Some(messages_from_diagnostic(e, src)),
+    }
+}
 
+fn export_serialize_error(e: &dyn Diagnostic) -> ExportPolicyResult {
+    ExportPolicyResult {
+        success: false,
+        json: None,
+        error_kind: Some(String::from("serialize")),
+        errors: Some(vec![ValidateMessage::without_span(format!(
+            "policy could not be converted to JSON: {}",
+            format_diagnostic(e, None)
+        ))]),
+    }
+}
+
+fn export_json(json: serde_json::Value) -> ExportPolicyResult {
     ExportPolicyResult {
         success: true,
         json: Some(json.to_string()),
+        error_kind: None,
+        errors: None,
+    }
+}
+
+#[wasm_bindgen(js_name = exportPolicy)]
+pub fn export_policy(input_policy_str: &str) -> ExportPolicyResult {
+    let policy = match Policy::parse(None, input_policy_str) {
+        Ok(policy) => policy,
+        Err(e) => return export_parse_error(input_policy_str, &e),
+    };
+    match policy.to_json() {
+        Ok(json) => export_json(json),
+        Err(e) => export_serialize_error(&e),
     }
 }
 
 #[wasm_bindgen(js_name = exportPolicies)]
 pub fn export_policies(input_policies_str: &str) -> ExportPolicyResult {
-    let Ok(policies) = PolicySet::from_str(input_policies_str) else {
-        return ExportPolicyResult {
-            success: false,
-            json: None,
-        };
+    let policies = match PolicySet::from_str(input_policies_str) {
+        Ok(policies) => policies,
+        Err(e) => return export_parse_error(input_policies_str, &e),
     };
+    match policies.to_json() {
+        Ok(json) => export_json(json),
+        Err(e) => export_serialize_error(&e),
+    }
+}
 
-    let Ok(json) = policies.to_json() else {
-        return ExportPolicyResult {
-            success: false,
-            json: None,
-        };
+#[wasm_bindgen(js_name = exportPolicyTemplate)]
+pub fn export_policy_template(input_policy_template_str: &str) -> ExportPolicyResult {
+    let policy = match Template::parse(None, input_policy_template_str) {
+        Ok(policy) => policy,
+        Err(e) => return export_parse_error(input_policy_template_str, &e),
     };
+    match policy.to_json() {
+        Ok(json) => export_json(json),
+        Err(e) => export_serialize_error(&e),
+    }
+}
 
-    ExportPolicyResult {
-        success: true,
-        json: Some(json.to_string()),
+#[wasm_bindgen(typescript_custom_section)]
+const IMPORT_POLICY_RESULT: &'static str = r#"
+export class ImportPolicyResult {
+  free(): void;
+  readonly success: boolean;
+  readonly policy: string | undefined;
+  readonly errors: Array<ValidateMessage> | undefined;
+}"#;
+
+#[wasm_bindgen(getter_with_clone, skip_typescript)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportPolicyResult {
+    #[wasm_bindgen(readonly)]
+    pub success: bool,
+    #[wasm_bindgen(readonly)]
+    pub policy: Option<String>,
+    errors: Option<Vec<ValidateMessage>>,
+}
+
+#[wasm_bindgen]
+impl ImportPolicyResult {
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Option<js_sys::Array> {
+        self.errors.as_deref().map(convert_messages_to_js_array)
     }
 }
 
-#[wasm_bindgen(js_name = exportPolicyTemplate)]
-pub fn export_policy_template(input_policy_template_str: &str) -> ExportPolicyResult {
-    let Ok(policy) = Template::parse(None, input_policy_template_str) else {
-        return ExportPolicyResult {
-            success: false,
-            json: None,
-        };
+fn import_error_result(errors: Vec<ValidateMessage>) -> ImportPolicyResult {
+    ImportPolicyResult {
+        success: false,
+        policy: None,
+        errors: Some(errors),
+    }
+}
+
+fn parse_import_json(input_json_str: &str) -> Result<serde_json::Value, ImportPolicyResult> {
+    serde_json::from_str(input_json_str).map_err(|e| {
+        import_error_result(vec![ValidateMessage::new(
+            format!("JSON could not be parsed: {e}"),
+            input_json_str,
+            serde_error_offset(input_json_str, &e),
+            0,
+        )])
+    })
+}
+
+/// Error converting valid JSON to a policy. Its labels point into Cedar text
+/// generated from the JSON rather than into the JSON itself, so it has no
+/// location.
+fn from_json_error(e: &dyn Diagnostic) -> ValidateMessage {
+    ValidateMessage::without_span(diagnostic_message(e))
+}
+
+fn format_import(cedar_str: &str, line_width: usize, indent_width: isize) -> ImportPolicyResult {
+    let config = Config {
+        line_width,
+        indent_width,
     };
+    match policies_str_to_pretty(cedar_str, &config) {
+        Ok(policy) => ImportPolicyResult {
+            success: true,
+            policy: Some(policy),
+            errors: None,
+        },
+        Err(e) => import_error_result(vec![ValidateMessage::without_span(format!(
+            "Format error: {e}"
+        ))]),
+    }
+}
 
-    let Ok(json) = policy.to_json() else {
-        return ExportPolicyResult {
-            success: false,
-            json: None,
-        };
+/// Convert the JSON (EST) form of a policy or template to formatted Cedar text
+#[wasm_bindgen(js_name = importPolicyJSON)]
+pub fn import_policy_json(
+    input_policy_json_str: &str,
+    line_width: usize,
+    indent_width: isize,
+) -> ImportPolicyResult {
+    let json = match parse_import_json(input_policy_json_str) {
+        Ok(json) => json,
+        Err(result) => return result,
+    };
+    // a template is rejected as a policy, so only report the policy error when
+    // the JSON is not a valid template either
+    let cedar_str = match Policy::from_json(None, json.clone()) {
+        Ok(policy) => policy.to_cedar(),
+        Err(e) => match Template::from_json(None, json) {
+            Ok(template) => Some(template.to_cedar()),
+            Err(_) => return import_error_result(vec![from_json_error(&e)]),
+        },
     };
+    match cedar_str {
+        Some(cedar_str) => format_import(&cedar_str, line_width, indent_width),
+        None => import_error_result(vec![ValidateMessage::without_span(String::from(
+            "policy could not be written as Cedar text",
+        ))]),
+    }
+}
 
-    ExportPolicyResult {
-        success: true,
-        json: Some(json.to_string()),
+/// Convert the JSON form of a policy set, with `staticPolicies` and
+/// `templates`, to formatted Cedar text
+#[wasm_bindgen(js_name = importPolicySetJSON)]
+pub fn import_policy_set_json(
+    input_policies_json_str: &str,
+    line_width: usize,
+    indent_width: isize,
+) -> ImportPolicyResult {
+    let json = match parse_import_json(input_policies_json_str) {
+        Ok(json) => json,
+        Err(result) => return result,
+    };
+    let pset = match PolicySet::from_json_value(json) {
+        Ok(pset) => pset,
+        Err(e) => return import_error_result(vec![from_json_error(&e)]),
+    };
+    match pset.to_cedar() {
+        Some(cedar_str) => format_import(&cedar_str, line_width, indent_width),
+        None => import_error_result(vec![ValidateMessage::without_span(String::from(
+            "template links cannot be written as Cedar text",
+        ))]),
+    }
+}
+
+/// Use `@id` annotations as policy and template IDs, matching the IDs the
+/// extension shows for each policy
+pub(crate) fn rename_from_id_annotation(
+    pset: &PolicySet,
+) -> Result<PolicySet, Box<PolicySetError>> {
+    let mut renamed = PolicySet::new();
+    for template in pset.templates() {
+        renamed.add_template(match template.annotation("id") {
+            Some(id) => template.new_id(PolicyId::new(id)),
+            None => template.clone(),
+        })?;
+    }
+    for policy in pset.policies() {
+        renamed.add(match policy.annotation("id") {
+            Some(id) => policy.new_id(PolicyId::new(id)),
+            None => policy.clone(),
+        })?;
+    }
+    Ok(renamed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn import_policy_json_round_trips() {
+        let src = "@id(\"view\")\npermit (\n  principal == ?principal,\n  action == Action::\"view\",\n  resource\n)\nwhen { resource.public };\n";
+        let json = export_policy_template(src).json.unwrap();
+        let result = import_policy_json(&json, 80, 2);
+        assert!(result.success, "{:?}", result.errors);
+        assert_eq!(result.policy.unwrap(), src);
+
+        let pset_json = export_policies("permit(principal, action, resource);")
+            .json
+            .unwrap();
+        let result = import_policy_set_json(&pset_json, 80, 2);
+        assert_eq!(
+            result.policy,
+            Some(String::from("permit (principal, action, resource);\n"))
+        );
+    }
+
+    #[test]
+    fn import_policy_json_reports_errors() {
+        let src = "{\n  \"effect\": \"permit\",\n  \"principal\": { \"op\": \"All\" }\n  \"action\": {}\n}";
+        let result = import_policy_json(src, 80, 2);
+        assert!(!result.success);
+        let errors = result.errors.unwrap();
+        assert_eq!(errors[0].start_line, 3);
+
+        let result = import_policy_json(r#"{ "effect": "allow" }"#, 80, 2);
+        assert!(!result.success);
+        assert!(result.errors.unwrap()[0].message.contains("allow"));
+
+        // the error is labeled within the entity type name, not the JSON
+        let src = r#"{ "effect": "permit", "principal": { "op": "==", "entity": { "type": "Bad Type", "id": "a" } }, "action": { "op": "All" }, "resource": { "op": "All" }, "conditions": [] }"#;
+        let errors = import_policy_json(src, 80, 2).errors.unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("Bad Type"));
+        assert_eq!((errors[0].offset, errors[0].length), (0, 0));
+    }
+
+    #[test]
+    fn export_policy_reports_parse_errors() {
+        let src = "permit(principal, action, resource) when { principal. };";
+        let result = export_policy(src);
+        assert!(!result.success);
+        assert_eq!(result.error_kind, Some(String::from("parse")));
+        let errors = result.errors.unwrap();
+        assert_eq!(&src[errors[0].offset..][..errors[0].length], "}");
+
+        let result = export_policy_template("permit(principal, action, resource);");
+        assert_eq!(result.error_kind, Some(String::from("parse")));
+        assert!(result.errors.is_some());
     }
 }
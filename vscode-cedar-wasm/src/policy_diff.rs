@@ -0,0 +1,341 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use cedar_policy::{PolicyId, PolicySet};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use wasm_bindgen::prelude::*;
+
+use crate::policy_outline::{parse_policy_outline, PolicyOutline};
+use crate::syntax_validator::parse_policies;
+use crate::utils::to_js_value;
+use crate::validate_message::{convert_messages_to_js_array, SourceRange, ValidateMessage};
+
+#[wasm_bindgen(typescript_custom_section)]
+const POLICY_DIFF_RESULT: &'static str = r#"
+export class PolicyPartChange {
+  readonly part: "effect" | "principal" | "action" | "resource" | "conditions" | "annotations";
+  readonly old: string;
+  readonly new: string;
+}
+export class PolicyChange {
+  readonly policyId: string;
+  readonly change: "added" | "removed" | "modified";
+  readonly oldRange: SourceRange | undefined;
+  readonly newRange: SourceRange | undefined;
+  readonly parts: Array<PolicyPartChange>;
+}
+export class PolicyDiffResult {
+  free(): void;
+  readonly success: boolean;
+  readonly changes: Array<PolicyChange> | undefined;
+  readonly oldErrors: Array<ValidateMessage> | undefined;
+  readonly newErrors: Array<ValidateMessage> | undefined;
+}"#;
+
+#[wasm_bindgen(getter_with_clone, skip_typescript)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PolicyDiffResult {
+    #[wasm_bindgen(readonly)]
+    pub success: bool,
+    changes: Option<Vec<PolicyChange>>,
+    old_errors: Option<Vec<ValidateMessage>>,
+    new_errors: Option<Vec<ValidateMessage>>,
+}
+
+#[wasm_bindgen]
+impl PolicyDiffResult {
+    #[wasm_bindgen(getter)]
+    pub fn changes(&self) -> JsValue {
+        self.changes
+            .as_ref()
+            .map_or(JsValue::UNDEFINED, to_js_value)
+    }
+
+    #[wasm_bindgen(getter, js_name = oldErrors)]
+    pub fn old_errors(&self) -> Option<js_sys::Array> {
+        self.old_errors.as_deref().map(convert_messages_to_js_array)
+    }
+
+    #[wasm_bindgen(getter, js_name = newErrors)]
+    pub fn new_errors(&self) -> Option<js_sys::Array> {
+        self.new_errors.as_deref().map(convert_messages_to_js_array)
+    }
+}
+
+/// A policy or template added, removed or modified between two versions of
+/// a policies document, where `parts` lists what changed in a modified one
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyChange {
+    pub policy_id: String,
+    pub change: String,
+    pub old_range: Option<SourceRange>,
+    pub new_range: Option<SourceRange>,
+    pub parts: Vec<PolicyPartChange>,
+}
+
+/// The old and new source of a part of a policy, with runs of whitespace
+/// outside string literals collapsed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PolicyPartChange {
+    pub part: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// A policy along with its EST, whose parts compare equal when the policies
+/// only differ in formatting
+struct ParsedPolicy<'a> {
+    outline: PolicyOutline,
+    est: Value,
+    src: &'a str,
+}
+
+impl ParsedPolicy<'_> {
+    /// Source text of `part` for display
+    fn part_text(&self, part: &str) -> String {
+        match part {
+            "effect" => self.outline.effect.clone(),
+            "conditions" => self
+                .outline
+                .conditions
+                .iter()
+                .map(|condition| collapse_whitespace(source(self.src, &condition.range)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            "annotations" => self
+                .outline
+                .annotations
+                .iter()
+                .map(|annotation| collapse_whitespace(source(self.src, &annotation.range)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            variable => self
+                .outline
+                .scope
+                .iter()
+                .find(|scope| scope.variable == variable)
+                .map(|scope| collapse_whitespace(&scope.constraint))
+                .unwrap_or_else(|| String::from(variable)),
+        }
+    }
+}
+
+const PARTS: [&str; 6] = [
+    "effect",
+    "principal",
+    "action",
+    "resource",
+    "conditions",
+    "annotations",
+];
+
+fn source<'a>(src: &'a str, range: &SourceRange) -> &'a str {
+    &src[range.offset..range.offset + range.length]
+}
+
+/// `text` with each run of whitespace outside string literals replaced by a
+/// single space
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let (mut in_string, mut escaped, mut space) = (false, false, false);
+    for c in text.trim().chars() {
+        if !in_string && c.is_whitespace() {
+            space = true;
+            continue;
+        }
+        if space {
+            collapsed.push(' ');
+            space = false;
+        }
+        if in_string {
+            in_string = escaped || c != '"';
+            escaped = !escaped && c == '\\';
+        } else {
+            in_string = c == '"';
+        }
+        collapsed.push(c);
+    }
+    collapsed
+}
+
+fn est(pset: &PolicySet, cedar_id: &str) -> Option<Value> {
+    let id = PolicyId::new(cedar_id);
+    match pset.policy(&id) {
+        Some(policy) => policy.to_json().ok(),
+        None => pset.template(&id)?.to_json().ok(),
+    }
+}
+
+fn parse(src: &str) -> Result<Vec<ParsedPolicy<'_>>, Vec<ValidateMessage>> {
+    let pset = parse_policies(src)?;
+    let outlines = parse_policy_outline(src)
+        .into_policies()
+        .unwrap_or_default();
+    Ok(outlines
+        .into_iter()
+        .filter_map(|outline| {
+            Some(ParsedPolicy {
+                est: est(&pset, &outline.cedar_id)?,
+                outline,
+                src,
+            })
+        })
+        .collect())
+}
+
+/// Key matching a policy across versions: its `@id` annotation, or the
+/// generated ID when it has none or shares its `@id` with an earlier policy
+fn keys(policies: &[ParsedPolicy]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    policies
+        .iter()
+        .map(|policy| {
+            let outline = &policy.outline;
+            if outline.policy_id != outline.cedar_id && seen.insert(&outline.policy_id) {
+                outline.policy_id.clone()
+            } else {
+                outline.cedar_id.clone()
+            }
+        })
+        .collect()
+}
+
+fn policy_changes(old: &[ParsedPolicy], new: &[ParsedPolicy]) -> Vec<PolicyChange> {
+    let old_keys = keys(old);
+    let new_keys = keys(new);
+    let old_by_key: HashMap<&str, &ParsedPolicy> =
+        old_keys.iter().map(String::as_str).zip(old).collect();
+    let new_key_set: HashSet<&str> = new_keys.iter().map(String::as_str).collect();
+
+    let mut changes = Vec::new();
+    for (key, new_policy) in new_keys.iter().zip(new) {
+        let Some(old_policy) = old_by_key.get(key.as_str()) else {
+            changes.push(PolicyChange {
+                policy_id: new_policy.outline.policy_id.clone(),
+                change: String::from("added"),
+                old_range: None,
+                new_range: Some(new_policy.outline.range.clone()),
+                parts: Vec::new(),
+            });
+            continue;
+        };
+        let parts: Vec<PolicyPartChange> = PARTS
+            .iter()
+            .filter(|part| old_policy.est[**part] != new_policy.est[**part])
+            .map(|part| PolicyPartChange {
+                part: part.to_string(),
+                old: old_policy.part_text(part),
+                new: new_policy.part_text(part),
+            })
+            .collect();
+        if !parts.is_empty() {
+            changes.push(PolicyChange {
+                policy_id: new_policy.outline.policy_id.clone(),
+                change: String::from("modified"),
+                old_range: Some(old_policy.outline.range.clone()),
+                new_range: Some(new_policy.outline.range.clone()),
+                parts,
+            });
+        }
+    }
+    for (key, old_policy) in old_keys.iter().zip(old) {
+        if !new_key_set.contains(key.as_str()) {
+            changes.push(PolicyChange {
+                policy_id: old_policy.outline.policy_id.clone(),
+                change: String::from("removed"),
+                old_range: Some(old_policy.outline.range.clone()),
+                new_range: None,
+                parts: Vec::new(),
+            });
+        }
+    }
+    changes
+}
+
+/// Policies added, removed and modified from `old_policies_str` to
+/// `new_policies_str`, matched by `@id` annotation or else by policy ID, and
+/// ignoring changes that only reformat a policy
+#[wasm_bindgen(js_name = diffPolicies)]
+pub fn diff_policies(old_policies_str: &str, new_policies_str: &str) -> PolicyDiffResult {
+    match (parse(old_policies_str), parse(new_policies_str)) {
+        (Ok(old), Ok(new)) => PolicyDiffResult {
+            success: true,
+            changes: Some(policy_changes(&old, &new)),
+            old_errors: None,
+            new_errors: None,
+        },
+        (old, new) => PolicyDiffResult {
+            success: false,
+            changes: None,
+            old_errors: old.err(),
+            new_errors: new.err(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn diff_policies_reports_semantic_changes() {
+        let old = r#"@id("view")
+permit(principal, action == Action::"view", resource) when { resource.public };
+@id("edit")
+permit(principal == User::"alice", action == Action::"edit", resource);
+@id("delete")
+forbid(principal, action == Action::"delete", resource);
+"#;
+        let new = r#"@id("edit")
+permit (
+  principal in Group::"editors",
+  action == Action::"edit",
+  resource
+);
+@id("view")
+permit (principal, action == Action::"view", resource)
+when {   resource.public   };
+@id("share")
+permit(principal, action == Action::"share", resource);
+"#;
+        let result = diff_policies(old, new);
+        assert!(result.success);
+        let changes = result.changes.unwrap();
+        let summary: Vec<(&str, &str)> = changes
+            .iter()
+            .map(|change| (change.policy_id.as_str(), change.change.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("edit", "modified"),
+                ("share", "added"),
+                ("delete", "removed")
+            ]
+        );
+        let parts = &changes[0].parts;
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].part, "principal");
+        assert_eq!(parts[0].old, r#"principal == User::"alice""#);
+        assert_eq!(parts[0].new, r#"principal in Group::"editors""#);
+    }
+
+    #[test]
+    fn diff_policies_matches_generated_ids() {
+        let old = "permit(principal, action, resource) when { context.a };\nforbid(principal, action, resource);";
+        let new = "permit(principal, action, resource) when { context.a  &&  context.b };\nforbid(principal, action, resource);";
+        let changes = diff_policies(old, new).changes.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].policy_id, "policy0");
+        assert_eq!(changes[0].parts[0].part, "conditions");
+        assert_eq!(changes[0].parts[0].new, "when { context.a && context.b }");
+
+        let errors = diff_policies(old, "permit(");
+        assert!(!errors.success);
+        assert!(errors.old_errors.is_none());
+        assert!(errors.new_errors.is_some());
+    }
+}
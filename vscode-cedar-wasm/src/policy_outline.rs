@@ -0,0 +1,287 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use cedar_policy::{Effect, PolicyId, PolicySet};
+use cedar_policy_core::parser::{cst, text_to_cst, Loc, Node};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use crate::syntax_validator::parse_policies;
+use crate::utils::to_js_value;
+use crate::validate_message::{convert_messages_to_js_array, SourceRange, ValidateMessage};
+
+#[wasm_bindgen(typescript_custom_section)]
+const POLICY_OUTLINE_RESULT: &'static str = r#"
+export class SourceRange {
+  readonly offset: number;
+  readonly length: number;
+  readonly startLine: number;
+  readonly startCharacter: number;
+  readonly endLine: number;
+  readonly endCharacter: number;
+}
+export class PolicyOutline {
+  readonly policyId: string;
+  readonly cedarId: string;
+  readonly kind: "policy" | "template";
+  readonly effect: "permit" | "forbid";
+  readonly annotations: Array<{ key: string; value: string | undefined; range: SourceRange }>;
+  readonly scope: Array<{ variable: "principal" | "action" | "resource"; constraint: string; range: SourceRange }>;
+  readonly conditions: Array<{ kind: "when" | "unless"; range: SourceRange }>;
+  readonly range: SourceRange;
+  readonly effectRange: SourceRange;
+}
+export class PolicyOutlineResult {
+  free(): void;
+  readonly success: boolean;
+  readonly policies: Array<PolicyOutline> | undefined;
+  readonly errors: Array<ValidateMessage> | undefined;
+}"#;
+
+#[wasm_bindgen(getter_with_clone, skip_typescript)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PolicyOutlineResult {
+    #[wasm_bindgen(readonly)]
+    pub success: bool,
+    policies: Option<Vec<PolicyOutline>>,
+    errors: Option<Vec<ValidateMessage>>,
+}
+
+#[wasm_bindgen]
+impl PolicyOutlineResult {
+    #[wasm_bindgen(getter)]
+    pub fn policies(&self) -> JsValue {
+        self.policies
+            .as_ref()
+            .map_or(JsValue::UNDEFINED, to_js_value)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Option<js_sys::Array> {
+        self.errors.as_deref().map(convert_messages_to_js_array)
+    }
+}
+
+impl PolicyOutlineResult {
+    pub(crate) fn into_policies(self) -> Option<Vec<PolicyOutline>> {
+        self.policies
+    }
+}
+
+/// A policy or template with the source range of each of its parts, where
+/// `policy_id` is the `@id` annotation when there is one and `cedar_id` is
+/// the `policy0`, `policy1`, ... ID Cedar generates
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyOutline {
+    pub policy_id: String,
+    pub cedar_id: String,
+    pub kind: String,
+    pub effect: String,
+    pub annotations: Vec<AnnotationOutline>,
+    pub scope: Vec<ScopeOutline>,
+    pub conditions: Vec<ConditionOutline>,
+    pub range: SourceRange,
+    pub effect_range: SourceRange,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AnnotationOutline {
+    pub key: String,
+    pub value: Option<String>,
+    pub range: SourceRange,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScopeOutline {
+    pub variable: String,
+    pub constraint: String,
+    pub range: SourceRange,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConditionOutline {
+    pub kind: String,
+    pub range: SourceRange,
+}
+
+fn range<T>(node: &Node<T>, src: &str) -> SourceRange {
+    match &node.loc {
+        Some(loc) => loc_range(loc, src),
+        None => SourceRange::new(src, 0, 0),
+    }
+}
+
+fn loc_range(loc: &Loc, src: &str) -> SourceRange {
+    SourceRange::new(src, loc.start(), loc.end() - loc.start())
+}
+
+fn ident(node: &Node<Option<cst::Ident>>) -> String {
+    node.as_inner()
+        .map(|ident| ident.to_string())
+        .unwrap_or_default()
+}
+
+fn policy_outline(
+    pset: &PolicySet,
+    cedar_id: PolicyId,
+    node: &Node<Option<cst::Policy>>,
+    src: &str,
+) -> Option<PolicyOutline> {
+    #[allow(irrefutable_let_patterns)]
+    let Some(cst::Policy::Policy(policy)) = node.as_inner() else {
+        return None;
+    };
+    let (kind, effect, annotation) = match pset.policy(&cedar_id) {
+        Some(policy) => ("policy", policy.effect(), policy.annotation("id")),
+        None => {
+            let template = pset.template(&cedar_id)?;
+            ("template", template.effect(), template.annotation("id"))
+        }
+    };
+    let annotations = policy
+        .annotations
+        .iter()
+        .filter_map(|annotation_node| {
+            let annotation = annotation_node.as_inner()?;
+            let key = ident(&annotation.key);
+            Some(AnnotationOutline {
+                value: pset
+                    .annotation(&cedar_id, &key)
+                    .or_else(|| pset.template_annotation(&cedar_id, &key))
+                    .map(|value| value.to_string()),
+                key,
+                range: range(annotation_node, src),
+            })
+        })
+        .collect();
+    let scope = policy
+        .variables
+        .iter()
+        .filter_map(|variable_node| {
+            let variable = variable_node.as_inner()?;
+            Some(ScopeOutline {
+                variable: ident(&variable.variable),
+                constraint: variable_node
+                    .loc
+                    .as_ref()
+                    .and_then(|loc| loc.snippet())
+                    .unwrap_or_default()
+                    .to_string(),
+                range: range(variable_node, src),
+            })
+        })
+        .collect();
+    let conditions = policy
+        .conds
+        .iter()
+        .filter_map(|cond_node| {
+            let cond = cond_node.as_inner()?;
+            Some(ConditionOutline {
+                kind: ident(&cond.cond),
+                range: range(cond_node, src),
+            })
+        })
+        .collect();
+    Some(PolicyOutline {
+        policy_id: annotation.unwrap_or(cedar_id.as_ref()).to_string(),
+        cedar_id: cedar_id.to_string(),
+        kind: kind.to_string(),
+        effect: String::from(match effect {
+            Effect::Permit => "permit",
+            Effect::Forbid => "forbid",
+        }),
+        annotations,
+        scope,
+        conditions,
+        range: range(node, src),
+        effect_range: range(&policy.effect, src),
+    })
+}
+
+#[wasm_bindgen(js_name = parsePolicyOutline)]
+pub fn parse_policy_outline(input_policies_str: &str) -> PolicyOutlineResult {
+    let pset = match parse_policies(input_policies_str) {
+        Ok(pset) => pset,
+        Err(errors) => {
+            return PolicyOutlineResult {
+                success: false,
+                policies: None,
+                errors: Some(errors),
+            }
+        }
+    };
+    // the text parsed to a policy set, so it also parses to a CST with the
+    // same generated IDs
+    let policies = text_to_cst::parse_policies(input_policies_str)
+        .ok()
+        .and_then(|cst| {
+            let nodes = cst.with_generated_policyids().ok()?;
+            Some(
+                nodes
+                    .filter_map(|(id, node)| {
+                        policy_outline(&pset, PolicyId::new(&id), node, input_policies_str)
+                    })
+                    .collect(),
+            )
+        })
+        .unwrap_or_default();
+    PolicyOutlineResult {
+        success: true,
+        policies: Some(policies),
+        errors: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_policy_outline_locates_parts() {
+        let src = r#"// "permit(" in a comment
+@id("view")
+@advice
+permit (principal == User::"alice", action, resource)
+when { resource.text like "forbid(*" };
+
+forbid (principal == ?principal, action, resource) unless { false };
+"#;
+        let result = parse_policy_outline(src);
+        assert!(result.success);
+        let policies = result.policies.unwrap();
+        assert_eq!(policies.len(), 2);
+
+        let view = &policies[0];
+        assert_eq!(view.policy_id, "view");
+        assert_eq!(view.cedar_id, "policy0");
+        assert_eq!(
+            (view.kind.as_str(), view.effect.as_str()),
+            ("policy", "permit")
+        );
+        assert_eq!(view.annotations.len(), 2);
+        assert_eq!(view.annotations[1].value, Some(String::new()));
+        assert_eq!(view.scope[0].constraint, "principal == User::\"alice\"");
+        assert_eq!(view.conditions[0].kind, "when");
+        assert_eq!(view.range.start_line, 1);
+        assert_eq!(
+            (
+                view.effect_range.start_line,
+                view.effect_range.start_character
+            ),
+            (3, 0)
+        );
+
+        let template = &policies[1];
+        assert_eq!(template.policy_id, "policy1");
+        assert_eq!(
+            (template.kind.as_str(), template.effect.as_str()),
+            ("template", "forbid")
+        );
+        assert_eq!(template.conditions[0].kind, "unless");
+        assert_eq!(
+            &src[template.range.offset..][..template.range.length],
+            "forbid (principal == ?principal, action, resource) unless { false };"
+        );
+    }
+}
@@ -0,0 +1,360 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use cedar_policy::{Authorizer, Entities, PolicySet, Schema};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use crate::authorizer::{decision_name, parse_request, policy_name};
+use crate::entities_validator::parse_entities_messages;
+use crate::json_spans::{serde_error_offset, JsonNode};
+use crate::syntax_validator::parse_policies;
+use crate::utils::to_js_value;
+use crate::validate_message::{convert_messages_to_js_array, SourceRange, ValidateMessage};
+
+#[wasm_bindgen(typescript_custom_section)]
+const RUN_POLICY_TESTS_RESULT: &'static str = r#"
+export class PolicyTestDiff {
+  readonly field: "decision" | "determiningPolicies";
+  readonly expected: string;
+  readonly actual: string;
+}
+export class PolicyTestResult {
+  readonly name: string;
+  readonly passed: boolean;
+  readonly range: SourceRange;
+  readonly decision: "allow" | "deny" | undefined;
+  readonly determiningPolicies: Array<string>;
+  readonly diffs: Array<PolicyTestDiff>;
+  readonly errors: Array<string>;
+}
+export class RunPolicyTestsResult {
+  free(): void;
+  readonly success: boolean;
+  readonly passed: number;
+  readonly failed: number;
+  readonly tests: Array<PolicyTestResult> | undefined;
+  readonly errors: Array<ValidateMessage> | undefined;
+}"#;
+
+#[wasm_bindgen(getter_with_clone, skip_typescript)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunPolicyTestsResult {
+    #[wasm_bindgen(readonly)]
+    pub success: bool,
+    #[wasm_bindgen(readonly)]
+    pub passed: usize,
+    #[wasm_bindgen(readonly)]
+    pub failed: usize,
+    tests: Option<Vec<PolicyTestResult>>,
+    errors: Option<Vec<ValidateMessage>>,
+}
+
+#[wasm_bindgen]
+impl RunPolicyTestsResult {
+    #[wasm_bindgen(getter)]
+    pub fn tests(&self) -> JsValue {
+        self.tests.as_ref().map_or(JsValue::UNDEFINED, to_js_value)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Option<js_sys::Array> {
+        self.errors.as_deref().map(convert_messages_to_js_array)
+    }
+}
+
+/// Outcome of one case of a `cedartests.json` file, where `range` is the
+/// case in the tests document, `diffs` lists each expectation that did not
+/// hold and `errors` explains why a case could not be evaluated at all
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyTestResult {
+    pub name: String,
+    pub passed: bool,
+    pub range: SourceRange,
+    pub decision: Option<String>,
+    pub determining_policies: Vec<String>,
+    pub diffs: Vec<PolicyTestDiff>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PolicyTestDiff {
+    pub field: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ExpectedDecision {
+    Allow,
+    Deny,
+}
+
+/// A named authorization request in a `cedartests.json` file, with the
+/// entities it adds to the shared ones and the response it expects.
+/// Determining policies are only checked when listed.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+struct PolicyTestCase {
+    name: String,
+    request: Value,
+    #[serde(default)]
+    entities: Vec<Value>,
+    decision: ExpectedDecision,
+    determining_policies: Option<Vec<String>>,
+}
+
+fn create_error_result(errors: Vec<ValidateMessage>) -> RunPolicyTestsResult {
+    RunPolicyTestsResult {
+        success: false,
+        passed: 0,
+        failed: 0,
+        tests: None,
+        errors: Some(errors),
+    }
+}
+
+fn format_policies(policies: &[String]) -> String {
+    serde_json::to_string(policies).unwrap_or_default()
+}
+
+/// Shared entities along with the entities a case adds to them
+fn case_entities(
+    shared: &[Value],
+    added: Vec<Value>,
+    schema: Option<&Schema>,
+) -> Result<Entities, String> {
+    let all = shared.iter().cloned().chain(added).collect();
+    Entities::from_json_value(Value::Array(all), schema).map_err(|e| e.to_string())
+}
+
+fn run_case(
+    schema: Option<&Schema>,
+    pset: &PolicySet,
+    shared_entities: &[Value],
+    case: PolicyTestCase,
+    range: SourceRange,
+) -> PolicyTestResult {
+    let mut result = PolicyTestResult {
+        name: case.name,
+        passed: false,
+        range,
+        decision: None,
+        determining_policies: Vec::new(),
+        diffs: Vec::new(),
+        errors: Vec::new(),
+    };
+    let request = parse_request(&case.request.to_string(), schema);
+    let entities = case_entities(shared_entities, case.entities, schema);
+    let (request, entities) = match (request, entities) {
+        (Ok(request), Ok(entities)) => (request, entities),
+        (request, entities) => {
+            if let Err(errors) = request {
+                result.errors.extend(errors.into_iter().map(|e| e.message));
+            }
+            if let Err(e) = entities {
+                result.errors.push(e);
+            }
+            return result;
+        }
+    };
+
+    let response = Authorizer::new().is_authorized(&request, pset, &entities);
+    let decision = decision_name(response.decision());
+    let mut determining_policies: Vec<String> = response
+        .diagnostics()
+        .reason()
+        .map(|id| policy_name(pset, id))
+        .collect();
+    determining_policies.sort();
+
+    let expected_decision = match case.decision {
+        ExpectedDecision::Allow => "allow",
+        ExpectedDecision::Deny => "deny",
+    };
+    if expected_decision != decision {
+        result.diffs.push(PolicyTestDiff {
+            field: String::from("decision"),
+            expected: expected_decision.to_string(),
+            actual: decision.to_string(),
+        });
+    }
+    if let Some(mut expected) = case.determining_policies {
+        expected.sort();
+        if expected != determining_policies {
+            result.diffs.push(PolicyTestDiff {
+                field: String::from("determiningPolicies"),
+                expected: format_policies(&expected),
+                actual: format_policies(&determining_policies),
+            });
+        }
+    }
+    result.passed = result.diffs.is_empty();
+    result.decision = Some(decision.to_string());
+    result.determining_policies = determining_policies;
+    result
+}
+
+/// Evaluate each case of the `cedartests.json` document `input_tests_str`,
+/// naming determining policies by their `@id` annotation when they have one
+fn run_policy_tests(
+    schema: Option<&Schema>,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_tests_str: &str,
+) -> RunPolicyTestsResult {
+    let pset = match parse_policies(input_policies_str) {
+        Ok(pset) => pset,
+        Err(errors) => return create_error_result(errors),
+    };
+    let shared_entities: Vec<Value> = match serde_json::from_str(input_entities_str) {
+        Ok(entities) => entities,
+        Err(e) => {
+            return create_error_result(vec![ValidateMessage::without_span(format!(
+                "invalid entities: {e}"
+            ))])
+        }
+    };
+    if let Err(errors) = parse_entities_messages(input_entities_str, schema) {
+        return create_error_result(errors);
+    }
+    let cases: Vec<PolicyTestCase> = match serde_json::from_str(input_tests_str) {
+        Ok(cases) => cases,
+        Err(e) => {
+            return create_error_result(vec![ValidateMessage::new(
+                format!("invalid policy tests: {e}"),
+                input_tests_str,
+                serde_error_offset(input_tests_str, &e),
+                0,
+            )])
+        }
+    };
+
+    let nodes = JsonNode::parse(input_tests_str);
+    let tests: Vec<PolicyTestResult> = cases
+        .into_iter()
+        .enumerate()
+        .map(|(i, case)| {
+            let range = match nodes.as_ref().and_then(|nodes| nodes.elements().get(i)) {
+                Some(node) => SourceRange::new(input_tests_str, node.offset, node.length),
+                None => SourceRange::new(input_tests_str, 0, 0),
+            };
+            run_case(schema, &pset, &shared_entities, case, range)
+        })
+        .collect();
+    let passed = tests.iter().filter(|test| test.passed).count();
+    RunPolicyTestsResult {
+        success: true,
+        passed,
+        failed: tests.len() - passed,
+        tests: Some(tests),
+        errors: None,
+    }
+}
+
+#[wasm_bindgen(js_name = runPolicyTests)]
+pub fn run_policy_tests_no_schema(
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_tests_str: &str,
+) -> RunPolicyTestsResult {
+    run_policy_tests(
+        None,
+        input_policies_str,
+        input_entities_str,
+        input_tests_str,
+    )
+}
+
+#[wasm_bindgen(js_name = runPolicyTestsSchemaJSON)]
+pub fn run_policy_tests_schema_json(
+    input_schema_str: &str,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_tests_str: &str,
+) -> RunPolicyTestsResult {
+    match Schema::from_json_str(input_schema_str) {
+        Ok(schema) => run_policy_tests(
+            Some(&schema),
+            input_policies_str,
+            input_entities_str,
+            input_tests_str,
+        ),
+        Err(e) => create_error_result(vec![ValidateMessage::without_span(e.to_string())]),
+    }
+}
+
+#[wasm_bindgen(js_name = runPolicyTestsSchemaCedar)]
+pub fn run_policy_tests_schema_cedar(
+    input_schema_str: &str,
+    input_policies_str: &str,
+    input_entities_str: &str,
+    input_tests_str: &str,
+) -> RunPolicyTestsResult {
+    match Schema::from_cedarschema_str(input_schema_str) {
+        Ok((schema, _warnings)) => run_policy_tests(
+            Some(&schema),
+            input_policies_str,
+            input_entities_str,
+            input_tests_str,
+        ),
+        Err(e) => create_error_result(vec![ValidateMessage::without_span(e.to_string())]),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    fn read_testdata(file: &str) -> String {
+        fs::read_to_string(format!("../testdata/policytests/{file}"))
+            .expect("Failed to read testdata")
+    }
+
+    fn run(tests_file: &str) -> RunPolicyTestsResult {
+        run_policy_tests_schema_cedar(
+            &read_testdata("cedarschema"),
+            &read_testdata("policies.cedar"),
+            &read_testdata("cedarentities.json"),
+            &read_testdata(tests_file),
+        )
+    }
+
+    #[test]
+    fn run_policy_tests_passes_fixtures() {
+        let result = run("cedartests.json");
+        assert!(result.success, "{:?}", result.errors);
+        let tests = result.tests.unwrap();
+        assert!(tests.iter().all(|test| test.passed), "{tests:?}");
+        assert_eq!((result.passed, result.failed), (4, 0));
+        assert_eq!(tests[1].determining_policies, vec![String::from("editors")]);
+        assert_eq!(tests[3].decision, Some(String::from("deny")));
+        assert_eq!(tests[3].range.start_line, 34);
+    }
+
+    #[test]
+    fn run_policy_tests_reports_diffs_and_errors() {
+        let result = run("failing.cedartests.json");
+        assert!(result.success);
+        assert_eq!((result.passed, result.failed), (0, 2));
+        let tests = result.tests.unwrap();
+        assert_eq!(tests[0].diffs.len(), 1);
+        assert_eq!(tests[0].diffs[0].field, "determiningPolicies");
+        assert_eq!(tests[0].diffs[0].expected, r#"["editors"]"#);
+        assert_eq!(tests[0].diffs[0].actual, r#"["owner"]"#);
+        assert_eq!(tests[1].decision, None);
+        assert_eq!(tests[1].errors.len(), 1);
+
+        let invalid = run_policy_tests_no_schema(
+            "permit(principal, action, resource);",
+            "[]",
+            r#"[{ "name": "x", "request": {}, "decision": "maybe" }]"#,
+        );
+        assert!(!invalid.success);
+        assert_eq!(invalid.errors.unwrap()[0].start_line, 0);
+    }
+}
@@ -1,21 +1,38 @@
 // Copyright Cedar Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use cedar_policy::{PolicySet, Schema, ValidationMode, Validator};
+use cedar_policy::{
+    PolicyId, PolicySet, Schema, ValidationError, ValidationMode, ValidationWarning, Validator,
+};
 use miette::Diagnostic;
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
 use wasm_bindgen::prelude::*;
 
-use crate::validate_message::{convert_messages_to_js_array, ValidateMessage};
+use crate::syntax_validator::parse_policies;
+use crate::validate_message::{
+    convert_messages_to_js_array, messages_from_diagnostic, ValidateMessage,
+};
 
 #[wasm_bindgen(typescript_custom_section)]
 const VALIDATE_POLICY_RESULT: &'static str = r#"
 export class ValidatePolicyResult {
   free(): void;
   readonly success: boolean;
+  readonly mode: "strict" | "permissive" | "partial" | undefined;
   readonly warnings: Array<ValidateMessage> | undefined;
   readonly errors: Array<ValidateMessage> | undefined;
+  readonly diagnostics: Array<ValidatePolicyDiagnostic> | undefined;
+}"#;
+
+#[wasm_bindgen(typescript_custom_section)]
+const VALIDATE_POLICY_DIAGNOSTIC: &'static str = r#"
+export class ValidatePolicyDiagnostic {
+  readonly policyId: string;
+  readonly kind: string;
+  readonly severity: "error" | "warning";
+  readonly message: string;
+  readonly help: string | undefined;
+  readonly labels: Array<ValidateMessage>;
 }"#;
 
 #[wasm_bindgen(getter_with_clone, skip_typescript)]
@@ -23,141 +40,345 @@ export class ValidatePolicyResult {
 pub struct ValidatePolicyResult {
     #[wasm_bindgen(readonly)]
     pub success: bool,
+    #[wasm_bindgen(readonly)]
+    pub mode: Option<String>,
     warnings: Option<Vec<ValidateMessage>>,
     errors: Option<Vec<ValidateMessage>>,
+    diagnostics: Option<Vec<ValidatePolicyDiagnostic>>,
+}
+
+/// A validation error or warning for one policy, with the `@id` annotation
+/// used as `policy_id` when the policy has one
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ValidatePolicyDiagnostic {
+    pub policy_id: String,
+    pub kind: String,
+    pub severity: String,
+    pub message: String,
+    pub help: Option<String>,
+    pub labels: Vec<ValidateMessage>,
 }
 
 #[wasm_bindgen]
 impl ValidatePolicyResult {
     #[wasm_bindgen(getter)]
     pub fn errors(&self) -> Option<js_sys::Array> {
-        self.errors.as_ref().map(convert_messages_to_js_array)
+        self.errors.as_deref().map(convert_messages_to_js_array)
     }
 
     #[wasm_bindgen(getter)]
     pub fn warnings(&self) -> Option<js_sys::Array> {
-        self.warnings.as_ref().map(convert_messages_to_js_array)
+        self.warnings.as_deref().map(convert_messages_to_js_array)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn diagnostics(&self) -> Option<js_sys::Array> {
+        self.diagnostics
+            .as_deref()
+            .map(convert_diagnostics_to_js_array)
+    }
+}
+
+#[cfg(any(test, feature = "cli", feature = "lsp"))]
+impl ValidatePolicyResult {
+    pub(crate) fn policy_diagnostics(&self) -> &[ValidatePolicyDiagnostic] {
+        self.diagnostics.as_deref().unwrap_or_default()
+    }
+}
+
+fn convert_diagnostics_to_js_array(diagnostics: &[ValidatePolicyDiagnostic]) -> js_sys::Array {
+    let arr = js_sys::Array::new_with_length(diagnostics.len() as u32);
+
+    for (i, d) in diagnostics.iter().enumerate() {
+        let vd = js_sys::Object::new();
+        js_sys::Reflect::set(
+            &vd,
+            &JsValue::from_str("policyId"),
+            &JsValue::from_str(&d.policy_id),
+        )
+        .unwrap();
+        js_sys::Reflect::set(&vd, &JsValue::from_str("kind"), &JsValue::from_str(&d.kind)).unwrap();
+        js_sys::Reflect::set(
+            &vd,
+            &JsValue::from_str("severity"),
+            &JsValue::from_str(&d.severity),
+        )
+        .unwrap();
+        js_sys::Reflect::set(
+            &vd,
+            &JsValue::from_str("message"),
+            &JsValue::from_str(&d.message),
+        )
+        .unwrap();
+        js_sys::Reflect::set(
+            &vd,
+            &JsValue::from_str("help"),
+            &d.help
+                .as_deref()
+                .map_or(JsValue::UNDEFINED, JsValue::from_str),
+        )
+        .unwrap();
+        js_sys::Reflect::set(
+            &vd,
+            &JsValue::from_str("labels"),
+            &convert_messages_to_js_array(&d.labels),
+        )
+        .unwrap();
+
+        arr.set(i as u32, JsValue::from(vd));
+    }
+
+    arr
+}
+
+fn validation_error_kind(e: &ValidationError) -> &'static str {
+    match e {
+        ValidationError::UnrecognizedEntityType(_) => "unrecognizedEntityType",
+        ValidationError::UnrecognizedActionId(_) => "unrecognizedActionId",
+        ValidationError::InvalidActionApplication(_) => "invalidActionApplication",
+        ValidationError::UnexpectedType(_) => "unexpectedType",
+        ValidationError::IncompatibleTypes(_) => "incompatibleTypes",
+        ValidationError::UnsafeAttributeAccess(_) => "unsafeAttributeAccess",
+        ValidationError::UnsafeOptionalAttributeAccess(_) => "unsafeOptionalAttributeAccess",
+        ValidationError::UnsafeTagAccess(_) => "unsafeTagAccess",
+        ValidationError::NoTagsAllowed(_) => "noTagsAllowed",
+        ValidationError::UndefinedFunction(_) => "undefinedFunction",
+        ValidationError::WrongNumberArguments(_) => "wrongNumberArguments",
+        ValidationError::FunctionArgumentValidation(_) => "functionArgumentValidation",
+        ValidationError::EmptySetForbidden(_) => "emptySetForbidden",
+        ValidationError::NonLitExtConstructor(_) => "nonLitExtConstructor",
+        ValidationError::HierarchyNotRespected(_) => "hierarchyNotRespected",
+        ValidationError::InternalInvariantViolation(_) => "internalInvariantViolation",
+        ValidationError::EntityDerefLevelViolation(_) => "entityDerefLevelViolation",
+        ValidationError::InvalidEnumEntity(_) => "invalidEnumEntity",
+        _ => "validationError",
+    }
+}
+
+fn validation_warning_kind(w: &ValidationWarning) -> &'static str {
+    match w {
+        ValidationWarning::MixedScriptString(_) => "mixedScriptString",
+        ValidationWarning::BidiCharsInString(_) => "bidiCharsInString",
+        ValidationWarning::BidiCharsInIdentifier(_) => "bidiCharsInIdentifier",
+        ValidationWarning::MixedScriptIdentifier(_) => "mixedScriptIdentifier",
+        ValidationWarning::ConfusableIdentifier(_) => "confusableIdentifier",
+        ValidationWarning::ImpossiblePolicy(_) => "impossiblePolicy",
+        _ => "validationWarning",
+    }
+}
+
+fn policy_diagnostic(
+    pset: &PolicySet,
+    policy_id: &PolicyId,
+    kind: &str,
+    severity: &str,
+    diagnostic: &dyn Diagnostic,
+    input_policies_str: &str,
+) -> ValidatePolicyDiagnostic {
+    let message = diagnostic.to_string();
+    let labels = diagnostic
+        .labels()
+        .map(|labels| {
+            labels
+                .map(|labeled_span| {
+                    ValidateMessage::new(
+                        labeled_span.label().unwrap_or(&message).to_string(),
+                        input_policies_str,
+                        labeled_span.offset(),
+                        labeled_span.len(),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    ValidatePolicyDiagnostic {
+        policy_id: pset
+            .annotation(policy_id, "id")
+            .or_else(|| pset.template_annotation(policy_id, "id"))
+            .unwrap_or(policy_id.as_ref())
+            .to_string(),
+        kind: kind.to_string(),
+        severity: severity.to_string(),
+        message,
+        help: diagnostic.help().map(|help| help.to_string()),
+        labels,
+    }
+}
+
+pub(crate) fn create_error_result(
+    mode: Option<ValidationMode>,
+    e: impl std::fmt::Display,
+) -> ValidatePolicyResult {
+    ValidatePolicyResult {
+        success: false,
+        mode: mode.map(validation_mode_name),
+        warnings: None,
+        errors: Some(vec![ValidateMessage::without_span(format!("{e}"))]),
+        diagnostics: None,
+    }
+}
+
+/// Parse the `"strict"`, `"permissive"` or `"partial"` mode passed from
+/// TypeScript, defaulting to strict validation
+pub(crate) fn parse_validation_mode(
+    validation_mode: Option<String>,
+) -> Result<ValidationMode, String> {
+    match validation_mode.as_deref() {
+        None | Some("strict") => Ok(ValidationMode::Strict),
+        Some("permissive") => Ok(ValidationMode::Permissive),
+        Some("partial") => Ok(ValidationMode::Partial),
+        Some(other) => Err(format!(
+            "unknown validation mode `{other}`, expected `strict`, `permissive` or `partial`"
+        )),
     }
 }
 
+fn validation_mode_name(mode: ValidationMode) -> String {
+    String::from(match mode {
+        ValidationMode::Permissive => "permissive",
+        ValidationMode::Partial => "partial",
+        _ => "strict",
+    })
+}
+
 #[wasm_bindgen(js_name = validatePolicySchemaJSON)]
 pub fn validate_policy_schema_json(
     input_schema_str: &str,
     input_policies_str: &str,
+    validation_mode: Option<String>,
 ) -> ValidatePolicyResult {
-    let schema = match Schema::from_json_str(&input_schema_str) {
+    let mode = match parse_validation_mode(validation_mode) {
+        Ok(mode) => mode,
+        Err(e) => return create_error_result(None, e),
+    };
+    let schema = match Schema::from_json_str(input_schema_str) {
         Ok(schema) => schema,
         Err(e) => {
             // example error message
             // JSON Schema file could not be parsed: expected `,` or `}` at line 8 column 38'
-            return ValidatePolicyResult {
-                success: false,
-                warnings: None,
-                errors: Some(vec![ValidateMessage {
-                    message: String::from(&format!("{e}")),
-                    offset: 0,
-                    length: 0,
-                }]),
-            };
+            return create_error_result(Some(mode), e);
         }
     };
-    return validate_policy_schema(schema, input_policies_str);
+    validate_policy_schema(schema, input_policies_str, mode)
 }
 
 #[wasm_bindgen(js_name = validatePolicySchemaCedar)]
 pub fn validate_policy_schema_cedar(
     input_schema_str: &str,
     input_policies_str: &str,
+    validation_mode: Option<String>,
 ) -> ValidatePolicyResult {
-    let schema_tuple = match Schema::from_cedarschema_str(&input_schema_str) {
+    let mode = match parse_validation_mode(validation_mode) {
+        Ok(mode) => mode,
+        Err(e) => return create_error_result(None, e),
+    };
+    let schema_tuple = match Schema::from_cedarschema_str(input_schema_str) {
         Ok(schema_tuple) => schema_tuple,
         Err(e) => {
-            return ValidatePolicyResult {
-                success: false,
-                warnings: None,
-                errors: Some(vec![ValidateMessage {
-                    message: String::from(&format!("{e}")),
-                    offset: 0,
-                    length: 0,
-                }]),
-            };
+            return create_error_result(Some(mode), e);
         }
     };
-    return validate_policy_schema(schema_tuple.0, input_policies_str);
+    validate_policy_schema(schema_tuple.0, input_policies_str, mode)
 }
 
-fn validate_policy_schema(schema: Schema, input_policies_str: &str) -> ValidatePolicyResult {
-    let validator = Validator::new(schema);
-    let pset = match PolicySet::from_str(&input_policies_str) {
+fn validate_policy_schema(
+    schema: Schema,
+    input_policies_str: &str,
+    mode: ValidationMode,
+) -> ValidatePolicyResult {
+    validate_parsed_policies(
+        &Validator::new(schema),
+        &parse_policies(input_policies_str),
+        input_policies_str,
+        mode,
+    )
+}
+
+/// Validate the result of `parse_policies`, reporting the parse errors when
+/// the policies could not be parsed
+pub(crate) fn validate_parsed_policies(
+    validator: &Validator,
+    parse_result: &Result<PolicySet, Vec<ValidateMessage>>,
+    input_policies_str: &str,
+    mode: ValidationMode,
+) -> ValidatePolicyResult {
+    let pset = match parse_result {
         Ok(pset) => pset,
-        Err(_e) => {
+        Err(parse_errs) => {
             return ValidatePolicyResult {
                 success: false,
+                mode: Some(validation_mode_name(mode)),
                 warnings: None,
-                errors: None,
+                errors: Some(parse_errs.clone()),
+                diagnostics: None,
             }
         }
     };
-    let result = validator.validate(&pset, ValidationMode::Strict);
-    
-    let mut validate_warnings = Vec::new();
-    result.validation_warnings().for_each(|w| {
-        w.labels().iter_mut().for_each(|labels| {
-            labels.as_mut().for_each(|labeled_span| {
-                let vpm = ValidateMessage {
-                    message: match labeled_span.label() {
-                        None => match w.help() {
-                            None => w.to_string(),
-                            Some(help) => format!("{}\n{}", w, help),
-                        },
-                        Some(msg) => match w.help() {
-                            None => format!("{}\n{}", w, msg),
-                            Some(help) => format!("{}\n{}\n{}", w, msg, help),
-                        },
-                    },
-                    offset: labeled_span.offset(),
-                    length: labeled_span.len(),
-                };
-                validate_warnings.push(vpm);
-            });
-        });
-    });
-    
+    let result = validator.validate(pset, mode);
+
+    let mut diagnostics: Vec<ValidatePolicyDiagnostic> = result
+        .validation_errors()
+        .map(|e| {
+            policy_diagnostic(
+                pset,
+                e.policy_id(),
+                validation_error_kind(e),
+                "error",
+                e,
+                input_policies_str,
+            )
+        })
+        .collect();
+    diagnostics.extend(result.validation_warnings().map(|w| {
+        policy_diagnostic(
+            pset,
+            w.policy_id(),
+            validation_warning_kind(w),
+            "warning",
+            w,
+            input_policies_str,
+        )
+    }));
+    let diagnostics = if diagnostics.is_empty() {
+        None
+    } else {
+        Some(diagnostics)
+    };
+
+    let validate_warnings: Vec<ValidateMessage> = result
+        .validation_warnings()
+        .flat_map(|w| messages_from_diagnostic(w, input_policies_str))
+        .collect();
+
     if result.validation_passed() {
-        return ValidatePolicyResult {
+        ValidatePolicyResult {
             success: true,
-            warnings: if validate_warnings.is_empty() { None } else { Some(validate_warnings) },
+            mode: Some(validation_mode_name(mode)),
+            warnings: if validate_warnings.is_empty() {
+                None
+            } else {
+                Some(validate_warnings)
+            },
             errors: None,
-        };
+            diagnostics,
+        }
     } else {
-        let mut validate_errs = Vec::new();
-        result.validation_errors().for_each(|e| {
-            e.labels().iter_mut().for_each(|labels| {
-                labels.as_mut().for_each(|labeled_span| {
-                    let vpm = ValidateMessage {
-                        message: match labeled_span.label() {
-                            None => match e.help() {
-                                None => e.to_string(),
-                                Some(help) => format!("{}\n{}", e, help),
-                            },
-                            Some(msg) => match e.help() {
-                                None => format!("{}\n{}", e, msg),
-                                Some(help) => format!("{}\n{}\n{}", e, msg, help),
-                            },
-                        },
-                        offset: labeled_span.offset(),
-                        length: labeled_span.len(),
-                    };
-                    validate_errs.push(vpm);
-                });
-            });
-        });
-        
-        return ValidatePolicyResult {
+        let validate_errs = result
+            .validation_errors()
+            .flat_map(|e| messages_from_diagnostic(e, input_policies_str))
+            .collect();
+
+        ValidatePolicyResult {
             success: false,
-            warnings: if validate_warnings.is_empty() { None } else { Some(validate_warnings) },
+            mode: Some(validation_mode_name(mode)),
+            warnings: if validate_warnings.is_empty() {
+                None
+            } else {
+                Some(validate_warnings)
+            },
             errors: Some(validate_errs),
-        };
+            diagnostics,
+        }
     }
 }
 
@@ -170,14 +391,62 @@ mod test {
         let result = validate_policy_schema_json(
             "{ \"entityTypes\": [], \"actions\": [] }",
             "permit(principal, action, resource);",
+            None,
         );
         assert!(matches!(
             result,
             ValidatePolicyResult {
                 success: false,
+                mode: _,
                 warnings: _,
                 errors: _,
+                diagnostics: _,
             }
         ));
     }
+
+    #[test]
+    fn validate_policy_diagnostics_use_id_annotation() {
+        let result = validate_policy_schema_cedar(
+            "entity User; action view appliesTo { principal: User, resource: User };",
+            "@id(\"ok\")\npermit(principal, action == Action::\"view\", resource);\n@id(\"bad\")\npermit(principal is Photo, action, resource);",
+            None,
+        );
+        assert!(!result.success);
+        let diagnostics = result.diagnostics.unwrap();
+        assert!(diagnostics.iter().all(|d| d.policy_id == "bad"));
+        assert!(diagnostics
+            .iter()
+            .any(|d| d.kind == "unrecognizedEntityType"
+                && d.severity == "error"
+                && d.labels.len() == 1));
+    }
+
+    #[test]
+    fn validate_policy_permissive_mode() {
+        let schema =
+            "entity User; entity Photo; action view appliesTo { principal: User, resource: Photo };";
+        let policy = "permit(principal, action == Action::\"view\", resource) when { [principal, resource].contains(principal) };";
+        let strict = validate_policy_schema_cedar(schema, policy, None);
+        assert!(!strict.success);
+        assert_eq!(strict.mode, Some(String::from("strict")));
+        let permissive =
+            validate_policy_schema_cedar(schema, policy, Some(String::from("permissive")));
+        assert!(permissive.success);
+        assert_eq!(permissive.mode, Some(String::from("permissive")));
+        let unknown = validate_policy_schema_cedar(schema, policy, Some(String::from("lenient")));
+        assert!(!unknown.success);
+        assert_eq!(unknown.mode, None);
+    }
+
+    #[test]
+    fn validate_policy_reports_parse_errors() {
+        let policy = "permit(principal, action, resource) when { principal. };";
+        let result = validate_policy_schema_cedar("entity User;", policy, None);
+        assert!(!result.success);
+        assert!(result.diagnostics.is_none());
+        let errors = result.errors.unwrap();
+        assert!(!errors.is_empty());
+        assert_eq!(&policy[errors[0].offset..][..errors[0].length], "}");
+    }
 }
@@ -0,0 +1,488 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+// Cedar does not keep comments when parsing a schema, so the schema is
+// formatted from its tokens, grouped by brackets, instead of from the parsed
+// schema. The result is parsed again to check that formatting did not change
+// the schema.
+
+use cedar_policy::SchemaFragment;
+use cedar_policy_formatter::Config;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Word,
+    Str,
+    Punct,
+    Comment,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token<'a> {
+    kind: Kind,
+    text: &'a str,
+    /// Number of line breaks between this token and the one before it
+    newlines: usize,
+}
+
+impl Token<'_> {
+    fn is(&self, text: &str) -> bool {
+        self.kind == Kind::Punct && self.text == text
+    }
+}
+
+#[derive(Debug)]
+struct Group<'a> {
+    open: Token<'a>,
+    items: Vec<Item<'a>>,
+    close: Token<'a>,
+}
+
+#[derive(Debug)]
+enum Item<'a> {
+    Token(Token<'a>),
+    Group(Group<'a>),
+}
+
+impl<'a> Item<'a> {
+    fn first(&self) -> &Token<'a> {
+        match self {
+            Item::Token(token) => token,
+            Item::Group(group) => &group.open,
+        }
+    }
+
+    fn last(&self) -> &Token<'a> {
+        match self {
+            Item::Token(token) => token,
+            Item::Group(group) => &group.close,
+        }
+    }
+
+    fn is(&self, text: &str) -> bool {
+        matches!(self, Item::Token(token) if token.is(text))
+    }
+
+    fn is_comment(&self) -> bool {
+        matches!(
+            self,
+            Item::Token(Token {
+                kind: Kind::Comment,
+                ..
+            })
+        )
+    }
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token<'_>>, String> {
+    let mut tokens = Vec::new();
+    let mut newlines = 0;
+    let mut chars = src.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        let (kind, end) = match c {
+            '\n' => {
+                newlines += 1;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '/' if src[start..].starts_with("//") => {
+                let end = src[start..].find('\n').map_or(src.len(), |i| start + i);
+                while chars.next_if(|(i, _)| *i < end).is_some() {}
+                (Kind::Comment, end)
+            }
+            '"' => {
+                let mut end = src.len();
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            chars.next();
+                        }
+                        '"' => {
+                            end = i + 1;
+                            break;
+                        }
+                        _ => {}
+                    }
+                }
+                (Kind::Str, end)
+            }
+            ':' if src[start..].starts_with("::") => {
+                chars.next();
+                (Kind::Punct, start + 2)
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                while chars
+                    .next_if(|(_, c)| c.is_alphanumeric() || *c == '_')
+                    .is_some()
+                {}
+                let end = chars.peek().map_or(src.len(), |(i, _)| *i);
+                (Kind::Word, end)
+            }
+            '{' | '}' | '[' | ']' | '(' | ')' | '<' | '>' | ',' | ';' | ':' | '?' | '=' | '@' => {
+                (Kind::Punct, start + 1)
+            }
+            c => return Err(format!("unexpected character `{c}`")),
+        };
+        tokens.push(Token {
+            kind,
+            text: src[start..end].trim_end(),
+            newlines,
+        });
+        newlines = 0;
+    }
+    Ok(tokens)
+}
+
+fn closing(open: &str) -> Option<&'static str> {
+    match open {
+        "{" => Some("}"),
+        "[" => Some("]"),
+        "(" => Some(")"),
+        "<" => Some(">"),
+        _ => None,
+    }
+}
+
+/// Group `tokens` by brackets, moving each separator ahead of any comments
+/// before it so that the comments stay after it on the same line
+fn group<'a>(
+    tokens: &mut std::vec::IntoIter<Token<'a>>,
+    close: Option<&str>,
+) -> Result<(Vec<Item<'a>>, Option<Token<'a>>), String> {
+    let mut items: Vec<Item<'a>> = Vec::new();
+    while let Some(token) = tokens.next() {
+        if token.kind == Kind::Punct && Some(token.text) == close {
+            return Ok((items, Some(token)));
+        }
+        if let Some(inner_close) = closing(token.text).filter(|_| token.kind == Kind::Punct) {
+            let (inner, Some(close_token)) = group(tokens, Some(inner_close))? else {
+                return Err(format!("unclosed `{}`", token.text));
+            };
+            items.push(Item::Group(Group {
+                open: token,
+                items: inner,
+                close: close_token,
+            }));
+        } else if token.is(",") || token.is(";") {
+            let comments = items.iter().rev().take_while(|i| i.is_comment()).count();
+            items.insert(items.len() - comments, Item::Token(token));
+        } else {
+            items.push(Item::Token(token));
+        }
+    }
+    match close {
+        Some(close) => Err(format!("expected `{close}`")),
+        None => Ok((items, None)),
+    }
+}
+
+fn space_between(prev: &Token, next: &Token) -> bool {
+    let no_space_before = [",", ";", ":", "?", "::", "(", "<", ">", ")", "]", "}"];
+    let no_space_after = ["@", "::", "(", "<", "[", "{"];
+    !(next.kind == Kind::Punct && no_space_before.contains(&next.text)
+        || prev.kind == Kind::Punct && no_space_after.contains(&prev.text))
+}
+
+/// Items without a trailing comma, which the schema syntax allows only in
+/// some lists
+fn without_trailing_comma<'i, 'a>(items: &'i [Item<'a>]) -> Vec<&'i Item<'a>> {
+    let last = items.iter().rposition(|item| !item.is_comment());
+    items
+        .iter()
+        .enumerate()
+        .filter(|(i, item)| !(Some(*i) == last && item.is(",")))
+        .map(|(_, item)| item)
+        .collect()
+}
+
+/// `items` on one line, or `None` when they contain a comment
+fn flat(items: &[&Item]) -> Option<String> {
+    let mut s = String::new();
+    let mut prev: Option<&Token> = None;
+    for item in items {
+        let text = match item {
+            Item::Token(token) if token.kind == Kind::Comment => return None,
+            Item::Token(token) => token.text.to_string(),
+            Item::Group(group) => flat_group(group)?,
+        };
+        if prev.is_some_and(|prev| space_between(prev, item.first())) {
+            s.push(' ');
+        }
+        s.push_str(&text);
+        prev = Some(item.last());
+    }
+    Some(s)
+}
+
+/// `group` on one line, or `None` when it has to be split over lines because
+/// it holds comments, annotations or declarations
+fn flat_group(group: &Group) -> Option<String> {
+    let items = without_trailing_comma(&group.items);
+    if group.open.text != "{" {
+        return Some(format!(
+            "{}{}{}",
+            group.open.text,
+            flat(&items)?,
+            group.close.text
+        ));
+    }
+    if items.iter().any(|item| item.is("@") || item.is(";")) {
+        return None;
+    }
+    match flat(&items)?.as_str() {
+        "" => Some(String::from("{}")),
+        inner => Some(format!("{{ {inner} }}")),
+    }
+}
+
+/// Whether `items[i]` ends an annotation, `@key` or `@key("value")`
+fn ends_annotation(items: &[&Item], i: usize) -> bool {
+    let before = |n: usize| i.checked_sub(n).map(|j| items[j]);
+    match items[i] {
+        Item::Group(group) if group.open.text == "(" => before(2).is_some_and(|item| item.is("@")),
+        Item::Token(Token {
+            kind: Kind::Word, ..
+        }) => {
+            before(1).is_some_and(|item| item.is("@"))
+                && !matches!(items.get(i + 1), Some(Item::Group(g)) if g.open.text == "(")
+        }
+        _ => false,
+    }
+}
+
+struct Printer<'c> {
+    out: String,
+    config: &'c Config,
+}
+
+impl Printer<'_> {
+    fn column(&self) -> usize {
+        let line_start = self.out.rfind('\n').map_or(0, |i| i + 1);
+        self.out[line_start..].chars().count()
+    }
+
+    fn push_space(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push(' ');
+        }
+    }
+
+    /// Start a line at `depth`, keeping one blank line where the source had
+    /// at least one
+    fn start_line(&mut self, depth: usize, blank_line: bool) {
+        if !self.out.is_empty() {
+            self.out.truncate(self.out.trim_end().len());
+            self.out.push('\n');
+            if blank_line {
+                self.out.push('\n');
+            }
+        }
+        let indent_width = usize::try_from(self.config.indent_width).unwrap_or(0);
+        self.out.push_str(&" ".repeat(depth * indent_width));
+    }
+
+    fn write_group(&mut self, group: &Group, depth: usize, trailing: usize) {
+        let breakable = matches!(group.open.text, "{" | "[");
+        if let Some(flat) = flat_group(group) {
+            if !breakable
+                || self.column() + flat.chars().count() + trailing <= self.config.line_width
+            {
+                self.out.push_str(&flat);
+                return;
+            }
+        }
+        self.out.push_str(group.open.text);
+        if breakable {
+            let separator = if group.items.iter().any(|item| item.is(";")) {
+                ";"
+            } else {
+                ","
+            };
+            self.write_lines(&group.items, depth + 1, Some(separator));
+            self.start_line(depth, false);
+        } else {
+            self.write_lines(&group.items, depth, None);
+        }
+        self.out.push_str(group.close.text);
+    }
+
+    /// Write `items`, starting a new line for each element ending with
+    /// `separator` and after each annotation and namespace when there is one
+    fn write_lines(&mut self, items: &[Item], depth: usize, separator: Option<&str>) {
+        let items = without_trailing_comma(items);
+        let broken = separator.is_some();
+        let is_separator = |item: &Item| separator.is_some_and(|separator| item.is(separator));
+        let mut line_start = broken;
+        let mut first = true;
+        let mut element_start = true;
+        let mut namespace = false;
+        let mut prev: Option<&Token> = None;
+        for (i, item) in items.iter().enumerate() {
+            match item {
+                Item::Token(token) if token.kind == Kind::Comment => {
+                    if token.newlines == 0 {
+                        self.push_space();
+                    } else {
+                        self.start_line(depth, !first && token.newlines > 1);
+                    }
+                    self.out.push_str(token.text);
+                    line_start = true;
+                    prev = None;
+                }
+                Item::Token(token) if is_separator(item) => {
+                    self.out.push_str(token.text);
+                    line_start = true;
+                    element_start = true;
+                    namespace = false;
+                    prev = None;
+                }
+                _ => {
+                    if line_start {
+                        self.start_line(depth, !first && item.first().newlines > 1);
+                        line_start = false;
+                    } else if prev.is_some_and(|prev| space_between(prev, item.first())) {
+                        self.out.push(' ');
+                    }
+                    if element_start {
+                        namespace =
+                            item.first().kind == Kind::Word && item.first().text == "namespace";
+                        element_start = false;
+                    }
+                    match item {
+                        Item::Token(token) => self.out.push_str(token.text),
+                        Item::Group(group) => {
+                            // the rest of the element stays on the same line
+                            let rest: Vec<&Item> = items[i + 1..]
+                                .iter()
+                                .take_while(|item| !is_separator(item))
+                                .copied()
+                                .collect();
+                            let trailing = flat(&rest).map_or(0, |rest| rest.len() + 2);
+                            self.write_group(group, depth, trailing);
+                        }
+                    }
+                    prev = Some(item.last());
+                    let ends_namespace = namespace && matches!(item, Item::Group(_));
+                    if broken && (ends_annotation(&items, i) || ends_namespace) {
+                        line_start = true;
+                        element_start = true;
+                        namespace = false;
+                        prev = None;
+                    }
+                }
+            }
+            first = false;
+        }
+    }
+}
+
+/// Normalized JSON for the schema in `src`, used to check that formatting
+/// does not change it
+fn schema_json(src: &str) -> Result<serde_json::Value, String> {
+    match SchemaFragment::from_cedarschema_str(src) {
+        Ok((fragment, _warnings)) => fragment.to_json_value().map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn comments(tokens: &[Token]) -> Vec<String> {
+    tokens
+        .iter()
+        .filter(|token| token.kind == Kind::Comment)
+        .map(|token| token.text.to_string())
+        .collect()
+}
+
+/// Format the Cedar schema in `src`, keeping its comments and annotations
+pub(crate) fn schema_str_to_pretty(src: &str, config: &Config) -> Result<String, String> {
+    let schema = schema_json(src).map_err(|e| format!("cannot parse input schema: {e}"))?;
+    let tokens = tokenize(src)?;
+    let expected_comments = comments(&tokens);
+    let (items, _) = group(&mut tokens.into_iter(), None)?;
+    let mut printer = Printer {
+        out: String::new(),
+        config,
+    };
+    printer.write_lines(&items, 0, Some(";"));
+    let mut formatted = printer.out.trim_end().to_string();
+    formatted.push('\n');
+
+    // make sure formatting changed only the layout
+    let formatted_schema =
+        schema_json(&formatted).map_err(|e| format!("formatted schema does not parse: {e}"))?;
+    if formatted_schema != schema || comments(&tokenize(&formatted)?) != expected_comments {
+        return Err(String::from("formatting changed the schema"));
+    }
+    Ok(formatted)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CONFIG: Config = Config {
+        line_width: 80,
+        indent_width: 2,
+    };
+
+    #[test]
+    fn format_schema_keeps_comments_and_annotations() {
+        let src = r#"// users
+namespace  App{
+@doc("a user")  entity User in [Group]{name:String,   // full name
+"job level"?:Long,};
+entity Group;
+
+
+// document types
+entity Document { owner: User, tags: Set<String>, viewers: Set<User>, editors: Set<User>, readers: Set<Group> } tags String;
+action view appliesTo{principal:[User],resource:[Document],context:{ip:ipaddr}};
+}"#;
+        let expected = r#"// users
+namespace App {
+  @doc("a user")
+  entity User in [Group] {
+    name: String, // full name
+    "job level"?: Long
+  };
+  entity Group;
+
+  // document types
+  entity Document {
+    owner: User,
+    tags: Set<String>,
+    viewers: Set<User>,
+    editors: Set<User>,
+    readers: Set<Group>
+  } tags String;
+  action view appliesTo {
+    principal: [User],
+    resource: [Document],
+    context: { ip: ipaddr }
+  };
+}
+"#;
+        let formatted = schema_str_to_pretty(src, &CONFIG).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(schema_str_to_pretty(&formatted, &CONFIG).unwrap(), expected);
+    }
+
+    #[test]
+    fn format_schema_fits_line_width() {
+        let src = "type Context = {a: Long, b: String};\naction \"view\" in [Action::\"read\"];";
+        let formatted = schema_str_to_pretty(src, &CONFIG).unwrap();
+        assert_eq!(
+            formatted,
+            "type Context = { a: Long, b: String };\naction \"view\" in [Action::\"read\"];\n"
+        );
+        let narrow = Config {
+            line_width: 20,
+            indent_width: 4,
+        };
+        assert_eq!(
+            schema_str_to_pretty(src, &narrow).unwrap(),
+            "type Context = {\n    a: Long,\n    b: String\n};\naction \"view\" in [\n    Action::\"read\"\n];\n"
+        );
+        assert!(schema_str_to_pretty("entity User {", &CONFIG).is_err());
+    }
+}
@@ -0,0 +1,575 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use cedar_policy::Schema;
+use cedar_policy_core::ast::Name;
+use cedar_policy_core::extensions::Extensions;
+use cedar_policy_core::parser::Loc;
+use cedar_policy_core::validator::json_schema::{
+    self, EntityTypeKind, Fragment, RecordType, TypeVariant,
+};
+use cedar_policy_core::validator::types::{Attributes, Type};
+use cedar_policy_core::validator::{RawName, ValidatorActionId, ValidatorEntityType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+use crate::json_spans::JsonNode;
+use crate::schema_validator::{parse_schema_cedar, parse_schema_json, ValidateSchemaResult};
+use crate::utils::to_js_value;
+use crate::validate_message::{
+    convert_messages_to_js_array, format_diagnostic, messages_from_diagnostic, SourceRange,
+    ValidateMessage,
+};
+
+#[wasm_bindgen(typescript_custom_section)]
+const INTROSPECT_SCHEMA_RESULT: &'static str = r#"
+export class SchemaAttribute {
+  readonly name: string;
+  readonly type: string;
+  readonly required: boolean;
+  readonly attributes: Array<SchemaAttribute>;
+  readonly range: SourceRange | undefined;
+}
+export class SchemaModel {
+  readonly namespaces: Array<{ name: string; range: SourceRange | undefined }>;
+  readonly commonTypes: Array<{ name: string; namespace: string; definition: string; range: SourceRange | undefined }>;
+  readonly entityTypes: Array<{
+    name: string;
+    namespace: string;
+    attributes: Array<SchemaAttribute>;
+    tags: string | undefined;
+    parents: Array<string>;
+    enumValues: Array<string> | undefined;
+    range: SourceRange | undefined;
+  }>;
+  readonly actions: Array<{
+    name: string;
+    id: string;
+    namespace: string;
+    principalTypes: Array<string>;
+    resourceTypes: Array<string>;
+    context: Array<SchemaAttribute>;
+    parents: Array<string>;
+    range: SourceRange | undefined;
+  }>;
+}
+export class IntrospectSchemaResult {
+  free(): void;
+  readonly success: boolean;
+  readonly schema: SchemaModel | undefined;
+  readonly errors: Array<ValidateMessage> | undefined;
+}"#;
+
+#[wasm_bindgen(getter_with_clone, skip_typescript)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IntrospectSchemaResult {
+    #[wasm_bindgen(readonly)]
+    pub success: bool,
+    schema: Option<SchemaModel>,
+    errors: Option<Vec<ValidateMessage>>,
+}
+
+#[wasm_bindgen]
+impl IntrospectSchemaResult {
+    #[wasm_bindgen(getter)]
+    pub fn schema(&self) -> JsValue {
+        self.schema.as_ref().map_or(JsValue::UNDEFINED, to_js_value)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Option<js_sys::Array> {
+        self.errors.as_deref().map(convert_messages_to_js_array)
+    }
+}
+
+/// Everything a schema defines, with type references resolved and the source
+/// range of each definition when it can be located
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaModel {
+    pub namespaces: Vec<NamespaceModel>,
+    pub common_types: Vec<CommonTypeModel>,
+    pub entity_types: Vec<EntityTypeModel>,
+    pub actions: Vec<ActionModel>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NamespaceModel {
+    pub name: String,
+    pub range: Option<SourceRange>,
+}
+
+/// A common type, where `definition` is the source text of its definition
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommonTypeModel {
+    pub name: String,
+    pub namespace: String,
+    pub definition: String,
+    pub range: Option<SourceRange>,
+}
+
+/// An entity type, where `parents` lists only the types declared in its
+/// `in` clause rather than every ancestor
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityTypeModel {
+    pub name: String,
+    pub namespace: String,
+    pub attributes: Vec<AttributeModel>,
+    pub tags: Option<String>,
+    pub parents: Vec<String>,
+    pub enum_values: Option<Vec<String>>,
+    pub range: Option<SourceRange>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionModel {
+    pub name: String,
+    pub id: String,
+    pub namespace: String,
+    pub principal_types: Vec<String>,
+    pub resource_types: Vec<String>,
+    pub context: Vec<AttributeModel>,
+    pub parents: Vec<String>,
+    pub range: Option<SourceRange>,
+}
+
+/// A record attribute, where `attributes` lists the attributes of a nested
+/// record and `type` is `Record` for one
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttributeModel {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub required: bool,
+    pub attributes: Vec<AttributeModel>,
+    pub range: Option<SourceRange>,
+}
+
+/// The schema source along with its JSON spans, which stand in for the
+/// locations Cedar only records when parsing the Cedar schema format
+struct Source<'a> {
+    src: &'a str,
+    json: Option<JsonNode>,
+}
+
+impl Source<'_> {
+    fn range(&self, loc: Option<&Loc>, json_path: &[&str]) -> Option<SourceRange> {
+        match loc {
+            Some(loc) => Some(SourceRange::new(
+                self.src,
+                loc.start(),
+                loc.end() - loc.start(),
+            )),
+            None => self
+                .json_node(json_path)
+                .map(|node| SourceRange::new(self.src, node.offset, node.length)),
+        }
+    }
+
+    fn json_node(&self, path: &[&str]) -> Option<&JsonNode> {
+        path.iter()
+            .try_fold(self.json.as_ref()?, |node, key| node.get(key))
+    }
+}
+
+fn record_type(ty: &json_schema::Type<RawName>) -> Option<&RecordType<RawName>> {
+    match ty {
+        json_schema::Type::Type {
+            ty: TypeVariant::Record(record),
+            ..
+        } => Some(record),
+        _ => None,
+    }
+}
+
+fn attribute_models(
+    source: &Source,
+    attrs: &Attributes,
+    raw_record: Option<&RecordType<RawName>>,
+    json_path: &[&str],
+) -> Vec<AttributeModel> {
+    attrs
+        .iter()
+        .map(|(name, attr)| {
+            let raw_ty = raw_record
+                .and_then(|raw| raw.attributes.get(name))
+                .map(|raw| &raw.ty);
+            let attr_path = [json_path, &[name.as_str()]].concat();
+            let (ty, attributes) = match attr.attr_type.as_ref() {
+                Type::Record { attrs, .. } => (
+                    String::from("Record"),
+                    attribute_models(
+                        source,
+                        attrs,
+                        raw_ty.and_then(record_type),
+                        &[attr_path.as_slice(), &["attributes"]].concat(),
+                    ),
+                ),
+                ty => (ty.to_string(), Vec::new()),
+            };
+            AttributeModel {
+                name: name.to_string(),
+                ty,
+                required: attr.is_required,
+                attributes,
+                range: source.range(raw_ty.and_then(|ty| ty.loc()), &attr_path),
+            }
+        })
+        .collect()
+}
+
+/// Resolve a type name written in namespace `ns` the way Cedar does, to the
+/// definition in `ns` when there is one and otherwise to the name as written
+fn resolve_name(name: &RawName, ns: Option<&Name>, defined: impl Fn(&str) -> bool) -> String {
+    let qualified = name.qualify_with_name(ns).to_string();
+    if defined(&qualified) {
+        qualified
+    } else {
+        name.to_string()
+    }
+}
+
+fn schema_model(source: &Source, schema: &Schema, fragment: &Fragment<RawName>) -> SchemaModel {
+    let entity_types: HashMap<String, &ValidatorEntityType> = schema
+        .as_ref()
+        .entity_types()
+        .map(|entity_type| (entity_type.name().to_string(), entity_type))
+        .collect();
+    let actions: HashMap<(String, String), &ValidatorActionId> = schema
+        .as_ref()
+        .action_ids()
+        .map(|action| {
+            let uid = action.name();
+            (
+                (
+                    uid.entity_type().to_string(),
+                    uid.eid().as_ref().to_string(),
+                ),
+                action,
+            )
+        })
+        .collect();
+
+    let mut model = SchemaModel::default();
+    for (ns, ns_def) in &fragment.0 {
+        let namespace = ns.as_ref().map(ToString::to_string).unwrap_or_default();
+        let qualify = |id: &dyn ToString| match ns {
+            Some(ns) => format!("{ns}::{}", id.to_string()),
+            None => id.to_string(),
+        };
+        model.namespaces.push(NamespaceModel {
+            name: namespace.clone(),
+            range: source.range(ns_def.loc.as_ref(), &[&namespace]),
+        });
+
+        for (id, common_type) in &ns_def.common_types {
+            let id = id.to_string();
+            let json_path = [namespace.as_str(), "commonTypes", &id];
+            let definition = match &common_type.loc {
+                Some(loc) => loc.snippet().unwrap_or_default(),
+                None => source
+                    .json_node(&json_path)
+                    .map(|node| &source.src[node.offset..][..node.length])
+                    .unwrap_or_default(),
+            };
+            model.common_types.push(CommonTypeModel {
+                name: qualify(&id),
+                namespace: namespace.clone(),
+                definition: definition.to_string(),
+                range: source.range(common_type.loc.as_ref(), &json_path),
+            });
+        }
+
+        for (id, entity_type) in &ns_def.entity_types {
+            let name = qualify(id);
+            let Some(validator_type) = entity_types.get(&name) else {
+                continue;
+            };
+            let id = id.to_string();
+            let json_path = [namespace.as_str(), "entityTypes", &id];
+            let (parents, raw_record, enum_values) = match &entity_type.kind {
+                EntityTypeKind::Standard(standard) => (
+                    standard
+                        .member_of_types
+                        .iter()
+                        .map(|parent| {
+                            resolve_name(parent, ns.as_ref(), |name| {
+                                entity_types.contains_key(name)
+                            })
+                        })
+                        .collect(),
+                    record_type(&standard.shape.0),
+                    None,
+                ),
+                EntityTypeKind::Enum { choices } => (
+                    Vec::new(),
+                    None,
+                    Some(choices.iter().map(ToString::to_string).collect()),
+                ),
+            };
+            model.entity_types.push(EntityTypeModel {
+                attributes: attribute_models(
+                    source,
+                    validator_type.attributes(),
+                    raw_record,
+                    &[&json_path[..], &["shape", "attributes"]].concat(),
+                ),
+                tags: validator_type.tag_type().map(ToString::to_string),
+                parents,
+                enum_values,
+                range: source.range(entity_type.loc.as_ref(), &json_path),
+                name,
+                namespace: namespace.clone(),
+            });
+        }
+
+        let action_type = qualify(&"Action");
+        for (id, action) in &ns_def.actions {
+            let Some(validator_action) = actions.get(&(action_type.clone(), id.to_string())) else {
+                continue;
+            };
+            let json_path = [namespace.as_str(), "actions", id];
+            let mut principal_types: Vec<String> = validator_action
+                .applies_to_principals()
+                .map(ToString::to_string)
+                .collect();
+            principal_types.sort();
+            let mut resource_types: Vec<String> = validator_action
+                .applies_to_resources()
+                .map(ToString::to_string)
+                .collect();
+            resource_types.sort();
+            let context = match validator_action.context_type() {
+                Type::Record { attrs, .. } => attribute_models(
+                    source,
+                    attrs,
+                    action
+                        .applies_to
+                        .as_ref()
+                        .and_then(|applies_to| record_type(&applies_to.context.0)),
+                    &[&json_path[..], &["appliesTo", "context", "attributes"]].concat(),
+                ),
+                _ => Vec::new(),
+            };
+            let parents = action
+                .member_of
+                .iter()
+                .flatten()
+                .map(|parent| {
+                    let ty = match &parent.ty {
+                        Some(ty) => resolve_name(ty, ns.as_ref(), |name| {
+                            actions.contains_key(&(name.to_string(), parent.id.to_string()))
+                        }),
+                        None => action_type.clone(),
+                    };
+                    match actions.get(&(ty.clone(), parent.id.to_string())) {
+                        Some(parent_action) => parent_action.name().to_string(),
+                        None => format!("{ty}::\"{}\"", parent.id.escape_debug()),
+                    }
+                })
+                .collect();
+            model.actions.push(ActionModel {
+                name: validator_action.name().to_string(),
+                id: id.to_string(),
+                namespace: namespace.clone(),
+                principal_types,
+                resource_types,
+                context,
+                parents,
+                range: source.range(action.loc.as_ref(), &json_path),
+            });
+        }
+    }
+    model
+}
+
+/// Model of `schema`, or the errors when it is `None`. The fragment the schema
+/// was built from is parsed again with `parse_fragment` for the locations the
+/// schema drops.
+fn introspect_result(
+    source: Source,
+    validate_result: ValidateSchemaResult,
+    schema: Option<Schema>,
+    parse_fragment: impl FnOnce() -> Result<Fragment<RawName>, Vec<ValidateMessage>>,
+) -> IntrospectSchemaResult {
+    let Some(schema) = schema else {
+        return IntrospectSchemaResult {
+            success: false,
+            schema: None,
+            errors: validate_result.into_errors(),
+        };
+    };
+    match parse_fragment() {
+        Ok(fragment) => IntrospectSchemaResult {
+            success: true,
+            schema: Some(schema_model(&source, &schema, &fragment)),
+            errors: None,
+        },
+        Err(errors) => IntrospectSchemaResult {
+            success: false,
+            schema: None,
+            errors: Some(errors),
+        },
+    }
+}
+
+#[wasm_bindgen(js_name = introspectSchemaJSON)]
+pub fn introspect_schema_json(input_schema_str: &str) -> IntrospectSchemaResult {
+    let (result, schema) = parse_schema_json(input_schema_str);
+    introspect_result(
+        Source {
+            src: input_schema_str,
+            json: JsonNode::parse(input_schema_str),
+        },
+        result,
+        schema,
+        || {
+            Fragment::from_json_str(input_schema_str)
+                .map_err(|e| vec![ValidateMessage::without_span(format_diagnostic(&e, None))])
+        },
+    )
+}
+
+#[wasm_bindgen(js_name = introspectSchemaCedar)]
+pub fn introspect_schema_cedar(input_schema_str: &str) -> IntrospectSchemaResult {
+    let (result, schema) = parse_schema_cedar(input_schema_str);
+    introspect_result(
+        Source {
+            src: input_schema_str,
+            json: None,
+        },
+        result,
+        schema,
+        || {
+            Fragment::from_cedarschema_str(input_schema_str, Extensions::all_available())
+                .map(|(fragment, _warnings)| fragment)
+                .map_err(|e| messages_from_diagnostic(&e, input_schema_str))
+        },
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn range_text<'a>(src: &'a str, range: &Option<SourceRange>) -> &'a str {
+        let range = range.as_ref().unwrap();
+        &src[range.offset..][..range.length]
+    }
+
+    #[test]
+    fn introspect_schema_cedar_resolves_definitions() {
+        let src = r#"namespace PhotoApp {
+  type Address = { street: String, zip?: String };
+  entity Group;
+  entity User in [Group] { address: Address, level: Long } tags String;
+  entity Color enum ["red", "blue"];
+  entity Photo;
+  action view appliesTo {
+    principal: [User],
+    resource: [Photo],
+    context: { ip: ipaddr }
+  };
+  action edit in [view] appliesTo { principal: User, resource: Photo };
+}
+"#;
+        let result = introspect_schema_cedar(src);
+        assert!(result.success);
+        let schema = result.schema.unwrap();
+        assert_eq!(schema.namespaces[0].name, "PhotoApp");
+        let namespace = range_text(src, &schema.namespaces[0].range);
+        assert!(namespace.starts_with("namespace PhotoApp {"), "{namespace}");
+        assert!(namespace.trim_end().ends_with('}'), "{namespace}");
+
+        assert_eq!(schema.common_types[0].name, "PhotoApp::Address");
+        assert_eq!(
+            range_text(src, &schema.common_types[0].range),
+            "type Address = { street: String, zip?: String };"
+        );
+
+        let user = schema
+            .entity_types
+            .iter()
+            .find(|entity_type| entity_type.name == "PhotoApp::User")
+            .unwrap();
+        assert_eq!(user.parents, vec!["PhotoApp::Group"]);
+        assert_eq!(user.tags.as_deref(), Some("String"));
+        assert_eq!(user.attributes[0].name, "address");
+        assert_eq!(user.attributes[0].ty, "Record");
+        let zip = &user.attributes[0].attributes[1];
+        assert_eq!((zip.name.as_str(), zip.required), ("zip", false));
+        assert_eq!(user.attributes[1].ty, "Long");
+        assert_eq!(range_text(src, &user.attributes[1].range), "Long");
+
+        let color = schema
+            .entity_types
+            .iter()
+            .find(|entity_type| entity_type.name == "PhotoApp::Color")
+            .unwrap();
+        assert_eq!(
+            color.enum_values,
+            Some(vec![String::from("red"), String::from("blue")])
+        );
+
+        let view = &schema.actions[1];
+        assert_eq!(view.name, "PhotoApp::Action::\"view\"");
+        assert_eq!(view.principal_types, vec!["PhotoApp::User"]);
+        assert_eq!(view.context[0].ty, "ipaddr");
+        assert_eq!(
+            schema.actions[0].parents,
+            vec!["PhotoApp::Action::\"view\""]
+        );
+    }
+
+    #[test]
+    fn introspect_schema_json_locates_definitions() {
+        let src = r#"{
+  "": {
+    "entityTypes": {
+      "User": {
+        "shape": {
+          "type": "Record",
+          "attributes": { "name": { "type": "String", "required": false } }
+        }
+      }
+    },
+    "actions": {
+      "view": { "appliesTo": { "principalTypes": ["User"], "resourceTypes": ["User"] } }
+    }
+  }
+}"#;
+        let result = introspect_schema_json(src);
+        assert!(result.success);
+        let schema = result.schema.unwrap();
+        let user = &schema.entity_types[0];
+        assert_eq!(user.name, "User");
+        assert!(range_text(src, &user.range).starts_with("{\n        \"shape\""));
+        assert!(!user.attributes[0].required);
+        assert_eq!(
+            range_text(src, &user.attributes[0].range),
+            r#"{ "type": "String", "required": false }"#
+        );
+        assert_eq!(schema.actions[0].name, "Action::\"view\"");
+        assert_eq!(schema.actions[0].resource_types, vec!["User"]);
+
+        let result = introspect_schema_json(r#"{ "": { "entityTypes": {}, "actions": 1 } }"#);
+        assert!(!result.success);
+        assert!(result.errors.is_some());
+    }
+
+    #[test]
+    fn introspect_result_reports_fragment_errors() {
+        let src = "entity User;";
+        let (validate_result, schema) = parse_schema_cedar(src);
+        let result = introspect_result(Source { src, json: None }, validate_result, schema, || {
+            Err(vec![ValidateMessage::without_span(String::from(
+                "no fragment",
+            ))])
+        });
+        assert!(!result.success);
+        assert_eq!(result.errors.unwrap()[0].message, "no fragment");
+    }
+}
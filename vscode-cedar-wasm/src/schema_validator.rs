@@ -1,14 +1,22 @@
 // Copyright Cedar Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use cedar_policy::SchemaWarning;
+use cedar_policy::Schema;
 use cedar_policy_core::extensions::Extensions;
-use cedar_policy_core::validator::{CedarSchemaError, ValidatorSchema};
-use miette::Diagnostic;
+use cedar_policy_core::validator::cedar_schema::SchemaWarning;
+use cedar_policy_core::validator::json_schema::Fragment;
+use cedar_policy_core::validator::{
+    RawName, SchemaError, ValidatorSchema, ValidatorSchemaFragment,
+};
+use miette::{Diagnostic, LabeledSpan};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
-use crate::validate_message::{convert_messages_to_js_array, ValidateMessage};
+use crate::json_spans::{serde_error_offset, JsonNode};
+use crate::validate_message::{
+    convert_messages_to_js_array, format_diagnostic, messages_from_diagnostic,
+    messages_from_labels, ValidateMessage,
+};
 
 #[wasm_bindgen(typescript_custom_section)]
 const VALIDATE_SCHEMA_RESULT: &'static str = r#"
@@ -29,7 +37,7 @@ pub struct ValidateSchemaResult {
 }
 
 fn messages(messages: &Option<Vec<ValidateMessage>>) -> Option<js_sys::Array> {
-    messages.as_ref().map(convert_messages_to_js_array)
+    messages.as_deref().map(convert_messages_to_js_array)
 }
 
 #[wasm_bindgen]
@@ -44,159 +52,390 @@ impl ValidateSchemaResult {
     }
 }
 
-fn format_diagnostic_message<T: Diagnostic>(diagnostic: &T) -> String {
-    diagnostic.help().map_or_else(
-        || format!("{diagnostic}"),
-        |help| format!("{diagnostic}\n{help}"),
-    )
+impl ValidateSchemaResult {
+    pub(crate) fn into_errors(self) -> Option<Vec<ValidateMessage>> {
+        self.errors
+    }
+
+    #[cfg(any(feature = "cli", feature = "lsp"))]
+    pub(crate) fn warning_messages(&self) -> &[ValidateMessage] {
+        self.warnings.as_deref().unwrap_or_default()
+    }
 }
 
 #[wasm_bindgen(js_name = validateSchemaJSON)]
 pub fn validate_schema_json(input_schema_str: &str) -> ValidateSchemaResult {
-    let result =
-        match ValidatorSchema::from_json_str(&input_schema_str, Extensions::all_available()) {
-            Ok(_schema) => {
-                // TODO: Check if there's a way to get warnings from successful schema validation in Cedar 4.8.2
-                ValidateSchemaResult {
-                    success: true,
-                    warnings: None,
-                    errors: None,
-                }
-            }
-            Err(e) => {
-                let mut warnings = Vec::new();
-                let mut errors = Vec::new();
+    parse_schema_json(input_schema_str).0
+}
 
-                let (offset, length) = if let Some(labels) = e.labels() {
-                    if let Some(label) = labels.into_iter().next() {
-                        (label.offset(), label.len())
-                    } else {
-                        (0, 0)
-                    }
+/// Validate a JSON schema, also returning the parsed schema when it is valid
+pub(crate) fn parse_schema_json(input_schema_str: &str) -> (ValidateSchemaResult, Option<Schema>) {
+    let fragment = match Fragment::<RawName>::from_json_str(input_schema_str) {
+        Ok(fragment) => fragment,
+        Err(e) => return (json_error_result(input_schema_str, &e), None),
+    };
+    // collected before the fragment is consumed by building the schema
+    let warnings = json_schema_warnings(input_schema_str, &fragment);
+    let schema = match ValidatorSchemaFragment::from_schema_fragment(fragment) {
+        Ok(fragment) => {
+            ValidatorSchema::from_schema_fragments([fragment], Extensions::all_available())
+        }
+        Err(e) => Err(e),
+    };
+    match schema {
+        Ok(schema) => (
+            ValidateSchemaResult {
+                success: true,
+                warnings: if warnings.is_empty() {
+                    None
                 } else {
-                    (0, 0)
-                };
-
-                let message = ValidateMessage {
-                    message: format_diagnostic_message(&e),
-                    offset,
-                    length,
-                };
-
-                match e.severity() {
-                    Some(miette::Severity::Warning) => warnings.push(message),
-                    _ => errors.push(message),
-                }
+                    Some(warnings)
+                },
+                errors: None,
+            },
+            Some(Schema::from(schema)),
+        ),
+        Err(e) => (json_error_result(input_schema_str, &e), None),
+    }
+}
 
-                ValidateSchemaResult {
-                    success: errors.is_empty(),
-                    warnings: if warnings.is_empty() { None } else { Some(warnings) },
-                    errors: if errors.is_empty() { None } else { Some(errors) },
-                }
-            }
-        };
-    result
+fn json_error_result(input_schema_str: &str, e: &SchemaError) -> ValidateSchemaResult {
+    let messages = match e {
+        SchemaError::JsonDeserialization(_) => {
+            // the serde error is private, so parse again for its position
+            let offset = serde_json::from_str::<Fragment<RawName>>(input_schema_str)
+                .err()
+                .map_or(0, |serde_error| {
+                    serde_error_offset(input_schema_str, &serde_error)
+                });
+            vec![ValidateMessage::new(
+                format_diagnostic(e, None),
+                input_schema_str,
+                offset,
+                0,
+            )]
+        }
+        _ => messages_from_labels(e, input_schema_str, |labeled_span| {
+            json_label_span(e, labeled_span, input_schema_str)
+        }),
+    };
+    error_result(e, messages)
 }
 
 #[wasm_bindgen(js_name = validateSchemaCedar)]
 pub fn validate_schema_cedar(input_schema_str: &str) -> ValidateSchemaResult {
-    let result =
-        match ValidatorSchema::from_cedarschema_str(&input_schema_str, Extensions::all_available())
-        {
-            Ok((_schema, warnings_iter)) => {
-                let mut warnings_vec = Vec::new();
-                for warning in warnings_iter {
-                    let offset_length = HasOffsetLength::offset_length(&warning);
-                    warnings_vec.push(ValidateMessage {
-                        message: format_diagnostic_message(&warning),
-                        offset: offset_length.offset,
-                        length: offset_length.length,
-                    });
-                }
+    parse_schema_cedar(input_schema_str).0
+}
+
+/// Validate a Cedar schema, also returning the parsed schema when it is valid
+pub(crate) fn parse_schema_cedar(input_schema_str: &str) -> (ValidateSchemaResult, Option<Schema>) {
+    match ValidatorSchema::from_cedarschema_str(input_schema_str, Extensions::all_available()) {
+        Ok((schema, warnings_iter)) => {
+            let warnings: Vec<ValidateMessage> = warnings_iter
+                .flat_map(|warning| messages_from_diagnostic(&warning, input_schema_str))
+                .collect();
+            (
                 ValidateSchemaResult {
                     success: true,
-                    warnings: if warnings_vec.is_empty() { None } else { Some(warnings_vec) },
+                    warnings: if warnings.is_empty() {
+                        None
+                    } else {
+                        Some(warnings)
+                    },
                     errors: None,
+                },
+                Some(Schema::from(schema)),
+            )
+        }
+        Err(e) => {
+            let messages = messages_from_diagnostic(&e, input_schema_str);
+            (error_result(&e, messages), None)
+        }
+    }
+}
+
+fn error_result(e: &dyn Diagnostic, messages: Vec<ValidateMessage>) -> ValidateSchemaResult {
+    match e.severity() {
+        Some(miette::Severity::Warning) => ValidateSchemaResult {
+            success: true,
+            warnings: Some(messages),
+            errors: None,
+        },
+        _ => ValidateSchemaResult {
+            success: false,
+            warnings: None,
+            errors: Some(messages),
+        },
+    }
+}
+
+/// Labels on errors from JSON schemas span the name they are about within a
+/// source of their own, so find where the JSON references that type instead,
+/// when it does so only once
+fn json_label_span(
+    diagnostic: &dyn Diagnostic,
+    labeled_span: &LabeledSpan,
+    src: &str,
+) -> Option<(usize, usize)> {
+    let contents = diagnostic
+        .source_code()?
+        .read_span(labeled_span.inner(), 0, 0)
+        .ok()?;
+    let name = std::str::from_utf8(contents.data()).ok()?;
+    let (offset, length) = (labeled_span.offset(), labeled_span.len());
+    if src.get(offset..offset + length) == Some(name) {
+        return Some((offset, length));
+    }
+    let root = JsonNode::parse(src)?;
+    let mut references = Vec::new();
+    json_type_references(src, &root, &mut references);
+    let matches: Vec<TypeReference> = references
+        .into_iter()
+        .filter(|reference| {
+            reference.name == name
+                || (!reference.namespace.is_empty()
+                    && format!("{}::{}", reference.namespace, reference.name) == name)
+        })
+        .collect();
+    // a name referred to more than once could be any of those references
+    match matches.as_slice() {
+        // inside the quotes of the JSON string
+        [reference] => Some((
+            reference.node.offset + 1,
+            reference.node.length.saturating_sub(2),
+        )),
+        _ => None,
+    }
+}
+
+/// A type name as written in a JSON schema, within `namespace`
+struct TypeReference<'a> {
+    namespace: &'a str,
+    name: String,
+    node: &'a JsonNode,
+}
+
+/// Every place a JSON schema refers to an entity or common type by name
+fn json_type_references<'a>(
+    src: &str,
+    root: &'a JsonNode,
+    references: &mut Vec<TypeReference<'a>>,
+) {
+    for (namespace, ns_node) in root.members() {
+        let mut push = |node: &'a JsonNode| {
+            if let Some(name) = node.string(src) {
+                references.push(TypeReference {
+                    namespace,
+                    name,
+                    node,
+                });
+            }
+        };
+        let mut types = Vec::new();
+        if let Some(entity_types) = ns_node.get("entityTypes") {
+            for (_, entity_type) in entity_types.members() {
+                if let Some(member_of) = entity_type.get("memberOfTypes") {
+                    member_of.elements().iter().for_each(&mut push);
                 }
+                types.extend(entity_type.get("shape"));
+                types.extend(entity_type.get("tags"));
             }
-            Err(e) => {
-                let mut warnings = Vec::new();
-                let mut errors = Vec::new();
-
-                let message = match HasOffsetLength::offset_length(&e) {
-                    offset_length => ValidateMessage {
-                        message: format_diagnostic_message(&e),
-                        offset: offset_length.offset,
-                        length: offset_length.length,
-                    },
+        }
+        if let Some(common_types) = ns_node.get("commonTypes") {
+            types.extend(common_types.members().iter().map(|(_, node)| node));
+        }
+        if let Some(actions) = ns_node.get("actions") {
+            for (_, action) in actions.members() {
+                let Some(applies_to) = action.get("appliesTo") else {
+                    continue;
                 };
-
-                match e.severity() {
-                    Some(miette::Severity::Warning) => warnings.push(message),
-                    _ => errors.push(message),
+                for key in ["principalTypes", "resourceTypes"] {
+                    if let Some(type_names) = applies_to.get(key) {
+                        type_names.elements().iter().for_each(&mut push);
+                    }
                 }
-
-                ValidateSchemaResult {
-                    success: errors.is_empty(),
-                    warnings: if warnings.is_empty() { None } else { Some(warnings) },
-                    errors: if errors.is_empty() { None } else { Some(errors) },
+                types.extend(applies_to.get("context"));
+            }
+        }
+        while let Some(node) = types.pop() {
+            let Some(type_node) = node.get("type") else {
+                continue;
+            };
+            match type_node.string(src).as_deref() {
+                Some("Record") => {
+                    if let Some(attributes) = node.get("attributes") {
+                        types.extend(attributes.members().iter().map(|(_, node)| node));
+                    }
+                }
+                Some("Set") => types.extend(node.get("element")),
+                Some("Entity" | "EntityOrCommon" | "Extension") => {
+                    if let Some(name) = node.get("name") {
+                        push(name);
+                    }
                 }
+                Some(_) => push(type_node),
+                None => {}
             }
-        };
-    result
+        }
+    }
 }
 
-struct OffsetLength {
-    offset: usize,
-    length: usize,
+/// Cedar only warns about shadowed names while converting the Cedar format,
+/// so convert the JSON schema to that format for its warnings, placing each
+/// at the JSON declaration of the name it is about
+fn json_schema_warnings(src: &str, fragment: &Fragment<RawName>) -> Vec<ValidateMessage> {
+    let Ok(cedar_src) = fragment.to_cedarschema() else {
+        return Vec::new();
+    };
+    let Ok((_, warnings)) =
+        Fragment::<RawName>::from_cedarschema_str(&cedar_src, Extensions::all_available())
+    else {
+        return Vec::new();
+    };
+    let root = JsonNode::parse(src);
+    let mut messages = Vec::new();
+    for warning in warnings {
+        // labels span the names in the Cedar format
+        let names: Vec<String> = warning
+            .labels()
+            .into_iter()
+            .flatten()
+            .filter_map(|labeled_span| {
+                let contents = warning
+                    .source_code()?
+                    .read_span(labeled_span.inner(), 0, 0)
+                    .ok()?;
+                Some(String::from_utf8_lossy(contents.data()).into_owned())
+            })
+            .collect();
+        let message = format_diagnostic(&warning, None);
+        if names.is_empty() {
+            messages.push(ValidateMessage::without_span(message));
+            continue;
+        }
+        for (index, name) in names.iter().enumerate() {
+            // the entity type comes before the common type that shadows it
+            let sections: &[&str] = match warning {
+                SchemaWarning::ShadowsEntity(_) if index == 0 => &["entityTypes"],
+                SchemaWarning::ShadowsEntity(_) => &["commonTypes"],
+                _ => &["entityTypes", "commonTypes"],
+            };
+            let declarations = root
+                .as_ref()
+                .map(|root| json_declarations(root, sections, name))
+                .unwrap_or_default();
+            // a name declared more than once could be any of them
+            messages.push(match declarations.as_slice() {
+                [node] => ValidateMessage::new(message.clone(), src, node.offset, node.length),
+                _ => ValidateMessage::without_span(message.clone()),
+            });
+        }
+    }
+    messages
 }
 
-trait HasOffsetLength {
-    fn offset_length(&self) -> OffsetLength;
+/// Declarations named `name` in the `sections` of every namespace of a JSON
+/// schema, such as `entityTypes`
+fn json_declarations<'a>(root: &'a JsonNode, sections: &[&str], name: &str) -> Vec<&'a JsonNode> {
+    root.members()
+        .iter()
+        .flat_map(|(_, ns_node)| sections.iter().filter_map(|section| ns_node.get(section)))
+        .filter_map(|section| section.get(name))
+        .collect()
 }
 
-impl HasOffsetLength for SchemaWarning {
-    fn offset_length(&self) -> OffsetLength {
-        let zero_offset = OffsetLength {
-            offset: 0,
-            length: 0,
-        };
+#[cfg(test)]
+mod test {
+    use super::*;
 
-        let labels = match self {
-            SchemaWarning::ShadowsEntity(warning, ..) => warning.labels(),
-            SchemaWarning::ShadowsBuiltin(warning, ..) => warning.labels(),
-            _ => return zero_offset,
-        };
+    fn message_text<'a>(src: &'a str, message: &ValidateMessage) -> &'a str {
+        &src[message.offset..][..message.length]
+    }
 
-        labels
-            .and_then(|labels| labels.into_iter().next())
-            .map(|label| OffsetLength {
-                offset: label.offset(),
-                length: label.len(),
-            })
-            .unwrap_or(zero_offset)
+    #[test]
+    fn validate_schema_json_reports_shadowing_warnings() {
+        let src = r#"{ "": {
+  "commonTypes": { "User": { "type": "String" } },
+  "entityTypes": { "User": {}, "decimal": {} },
+  "actions": {}
+} }"#;
+        let result = validate_schema_json(src);
+        assert!(result.success);
+        let warnings = result.warnings.unwrap();
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(
+            warnings[0].message,
+            "The common type name User shadows an entity name"
+        );
+        assert_eq!(message_text(src, &warnings[0]), "{}");
+        assert_eq!(message_text(src, &warnings[1]), r#"{ "type": "String" }"#);
+        assert!(warnings[2]
+            .message
+            .starts_with("The name `decimal` shadows"));
     }
-}
 
-impl HasOffsetLength for CedarSchemaError {
-    fn offset_length(&self) -> OffsetLength {
-        let zero_offset = OffsetLength {
-            offset: 0,
-            length: 0,
+    #[test]
+    fn validate_schema_json_warns_like_cedar_schemas() {
+        let cedar = "type User = String;\nentity User;\nentity decimal;\ntype ipaddr = Long;\n";
+        let json = r#"{ "": {
+  "commonTypes": { "User": { "type": "String" }, "ipaddr": { "type": "Long" } },
+  "entityTypes": { "User": {}, "decimal": {} },
+  "actions": {}
+} }"#;
+        let messages = |result: ValidateSchemaResult| {
+            let mut messages: Vec<String> = result
+                .warnings
+                .unwrap_or_default()
+                .into_iter()
+                .map(|warning| warning.message)
+                .collect();
+            messages.sort();
+            messages
         };
+        let cedar_warnings = messages(validate_schema_cedar(cedar));
+        assert_eq!(cedar_warnings.len(), 4);
+        assert_eq!(messages(validate_schema_json(json)), cedar_warnings);
+    }
 
-        let labels = match self {
-            CedarSchemaError::Parsing(parse_error) => parse_error.labels(),
-            CedarSchemaError::Schema(schema_error) => schema_error.labels(),
-            _ => return zero_offset,
-        };
+    #[test]
+    fn validate_schema_json_leaves_ambiguous_warnings_unplaced() {
+        let src = r#"{
+  "A": { "entityTypes": { "decimal": {} }, "actions": {} },
+  "B": { "entityTypes": { "decimal": {} }, "actions": {} }
+}"#;
+        let warnings = validate_schema_json(src).warnings.unwrap();
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings
+            .iter()
+            .all(|warning| warning.offset == 0 && warning.length == 0));
+    }
 
-        labels
-            .and_then(|labels| labels.into_iter().next())
-            .map(|label| OffsetLength {
-                offset: label.offset(),
-                length: label.len(),
-            })
-            .unwrap_or(zero_offset)
+    #[test]
+    fn validate_schema_json_positions_errors() {
+        let src = r#"{ "": {
+  "entityTypes": {
+    "User": { "shape": { "type": "Record", "attributes": { "a": { "type": "Address" } } } }
+  },
+  "actions": {}
+} }"#;
+        let errors = validate_schema_json(src).errors.unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(message_text(src, &errors[0]), "Address");
+
+        // the attribute named like the undeclared type is not a reference to it
+        let src = r#"{ "": {
+  "entityTypes": {
+    "User": { "shape": { "type": "Record", "attributes": { "Address": { "type": "Long" } } } },
+    "Home": { "memberOfTypes": ["Address"] }
+  },
+  "actions": {}
+} }"#;
+        let errors = validate_schema_json(src).errors.unwrap();
+        assert_eq!(errors[0].start_line, 3);
+        assert_eq!(message_text(src, &errors[0]), "Address");
+
+        let src = "{ \"\": {\n  \"entityTypes\": { \"User\": { \"parents\": [] } },\n  \"actions\": {}\n} }";
+        let errors = validate_schema_json(src).errors.unwrap();
+        assert_eq!((errors[0].start_line, errors[0].length), (1, 0));
     }
 }
@@ -2,12 +2,13 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use cedar_policy::PolicySet;
-use miette::Diagnostic;
 use serde::{Deserialize, Serialize};
 use std::str::FromStr;
 use wasm_bindgen::prelude::*;
 
-use crate::validate_message::{convert_messages_to_js_array, ValidateMessage};
+use crate::validate_message::{
+    convert_messages_to_js_array, messages_from_diagnostic, ValidateMessage,
+};
 
 // use https://github.com/cloudflare/serde-wasm-bindgen ?
 
@@ -37,49 +38,30 @@ pub struct ValidateSyntaxResult {
 impl ValidateSyntaxResult {
     #[wasm_bindgen(getter)]
     pub fn errors(&self) -> Option<js_sys::Array> {
-        self.errors.as_ref().map(convert_messages_to_js_array)
+        self.errors.as_deref().map(convert_messages_to_js_array)
     }
 }
 
-#[wasm_bindgen(js_name = validateSyntax)]
-pub fn validate_syntax(input_policies_str: &str) -> ValidateSyntaxResult {
-    let parse_result = match PolicySet::from_str(input_policies_str) {
-        Err(parse_errs) => {
-            let mut syntax_errs = Vec::new();
-            parse_errs.iter().for_each(|parse_err| {
-                if parse_err.labels().into_iter().count() == 0 {
-                    syntax_errs.push(ValidateMessage {
-                        message: parse_err.to_string(),
-                        length: 0,
-                        offset: 0,
-                    });
-                } else {
-                    parse_err.labels().into_iter().for_each(|labels| {
-                        labels.into_iter().for_each(|labeled_span| {
-                            let message = match (labeled_span.label(), parse_err.help()) {
-                                (None, None) => parse_err.to_string(),
-                                (None, Some(help)) => format!("{}\n{}", parse_err, help),
-                                (Some(msg), None) => format!("{}\n{}", parse_err, msg),
-                                (Some(msg), Some(help)) => {
-                                    format!("{}\n{}\n{}", parse_err, msg, help)
-                                }
-                            };
-                            syntax_errs.push(ValidateMessage {
-                                message: message,
-                                length: labeled_span.len(),
-                                offset: labeled_span.offset(),
-                            });
-                        });
-                    });
-                };
-            });
-            ValidateSyntaxResult {
-                success: false,
-                policies: None,
-                templates: None,
-                errors: Some(syntax_errs),
-            }
-        }
+/// Parse `input_policies_str`, converting parse errors to messages
+pub(crate) fn parse_policies(input_policies_str: &str) -> Result<PolicySet, Vec<ValidateMessage>> {
+    PolicySet::from_str(input_policies_str).map_err(|parse_errs| {
+        parse_errs
+            .iter()
+            .flat_map(|parse_err| messages_from_diagnostic(parse_err, input_policies_str))
+            .collect()
+    })
+}
+
+pub(crate) fn syntax_result(
+    parse_result: &Result<PolicySet, Vec<ValidateMessage>>,
+) -> ValidateSyntaxResult {
+    match parse_result {
+        Err(syntax_errs) => ValidateSyntaxResult {
+            success: false,
+            policies: None,
+            templates: None,
+            errors: Some(syntax_errs.clone()),
+        },
         Ok(policy_set) => {
             let policies_count = policy_set.policies().count();
             let templates_count = policy_set.templates().count();
@@ -90,8 +72,12 @@ pub fn validate_syntax(input_policies_str: &str) -> ValidateSyntaxResult {
                 errors: None,
             }
         }
-    };
-    parse_result
+    }
+}
+
+#[wasm_bindgen(js_name = validateSyntax)]
+pub fn validate_syntax(input_policies_str: &str) -> ValidateSyntaxResult {
+    syntax_result(&parse_policies(input_policies_str))
 }
 
 #[cfg(test)]
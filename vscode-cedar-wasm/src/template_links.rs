@@ -0,0 +1,283 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use cedar_policy::{EntityUid, PolicyId, PolicySet, Schema, SlotId, ValidationMode, Validator};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+use crate::json_spans::JsonNode;
+use crate::policy::rename_from_id_annotation;
+use crate::validate_message::{convert_messages_to_js_array, ValidateMessage};
+
+#[wasm_bindgen(typescript_custom_section)]
+const VALIDATE_TEMPLATE_LINKS_RESULT: &'static str = r#"
+export class ValidateTemplateLinksResult {
+  free(): void;
+  readonly success: boolean;
+  readonly links: number | undefined;
+  readonly errors: Array<ValidateMessage> | undefined;
+}"#;
+
+#[wasm_bindgen(getter_with_clone, skip_typescript)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValidateTemplateLinksResult {
+    #[wasm_bindgen(readonly)]
+    pub success: bool,
+    #[wasm_bindgen(readonly)]
+    pub links: Option<usize>,
+    errors: Option<Vec<ValidateMessage>>,
+}
+
+#[wasm_bindgen]
+impl ValidateTemplateLinksResult {
+    #[wasm_bindgen(getter)]
+    pub fn errors(&self) -> Option<js_sys::Array> {
+        self.errors.as_deref().map(convert_messages_to_js_array)
+    }
+}
+
+#[cfg(feature = "cli")]
+impl ValidateTemplateLinksResult {
+    pub(crate) fn into_errors(self) -> Option<Vec<ValidateMessage>> {
+        self.errors
+    }
+}
+
+/// Template link in the `cedartemplatelinks.json` format used by the Cedar CLI
+#[derive(Debug, Deserialize)]
+struct TemplateLink {
+    template_id: String,
+    link_id: String,
+    args: HashMap<String, String>,
+}
+
+fn create_error_result(e: impl std::fmt::Display) -> ValidateTemplateLinksResult {
+    ValidateTemplateLinksResult {
+        success: false,
+        links: None,
+        errors: Some(vec![ValidateMessage::without_span(e.to_string())]),
+    }
+}
+
+fn slot_id(slot: &str) -> Option<SlotId> {
+    match slot {
+        "?principal" => Some(SlotId::principal()),
+        "?resource" => Some(SlotId::resource()),
+        _ => None,
+    }
+}
+
+pub(crate) fn validate_template_links(
+    schema: Schema,
+    input_policies_str: &str,
+    input_links_str: &str,
+) -> ValidateTemplateLinksResult {
+    let mut pset = match PolicySet::from_str(input_policies_str)
+        .map_err(|e| e.to_string())
+        .and_then(|pset| rename_from_id_annotation(&pset).map_err(|e| e.to_string()))
+    {
+        Ok(pset) => pset,
+        Err(e) => return create_error_result(format!("policies could not be parsed: {e}")),
+    };
+    let links: Vec<serde_json::Value> = match serde_json::from_str(input_links_str) {
+        Ok(links) => links,
+        Err(e) => return create_error_result(format!("template links could not be parsed: {e}")),
+    };
+    let Some(root) = JsonNode::parse(input_links_str) else {
+        return create_error_result("template links could not be parsed");
+    };
+
+    let mut errors = Vec::new();
+    let mut link_nodes: HashMap<PolicyId, &JsonNode> = HashMap::new();
+    for (link_json, link_node) in links.into_iter().zip(root.elements()) {
+        let error = |node: &JsonNode, message: String| {
+            ValidateMessage::new(message, input_links_str, node.offset, node.length)
+        };
+        let link: TemplateLink = match serde_json::from_value(link_json) {
+            Ok(link) => link,
+            Err(e) => {
+                errors.push(error(link_node, format!("invalid template link: {e}")));
+                continue;
+            }
+        };
+        let template_id_node = link_node.find(&["template_id"]);
+        let link_id_node = link_node.find(&["link_id"]);
+        let args_node = link_node.find(&["args"]);
+
+        let template_id = PolicyId::new(&link.template_id);
+        let Some(template) = pset.template(&template_id) else {
+            errors.push(error(
+                template_id_node,
+                format!("template `{}` not found", link.template_id),
+            ));
+            continue;
+        };
+        let expected_slots: HashSet<SlotId> = template.slots().cloned().collect();
+
+        let mut vals = HashMap::new();
+        for (slot, value) in &link.args {
+            let value_node = args_node.find(&[slot]);
+            match slot_id(slot) {
+                Some(slot_id) if expected_slots.contains(&slot_id) => {
+                    match EntityUid::from_str(value) {
+                        Ok(uid) => {
+                            vals.insert(slot_id, uid);
+                        }
+                        Err(e) => errors.push(error(
+                            value_node,
+                            format!("invalid entity `{value}` for slot `{slot}`: {e}"),
+                        )),
+                    }
+                }
+                _ => errors.push(error(
+                    value_node,
+                    format!(
+                        "template `{}` does not have a `{slot}` slot",
+                        link.template_id
+                    ),
+                )),
+            }
+        }
+        let mut missing_slots: Vec<String> = expected_slots
+            .iter()
+            .filter(|slot| {
+                !link
+                    .args
+                    .keys()
+                    .any(|arg| slot_id(arg).as_ref() == Some(*slot))
+            })
+            .map(|slot| slot.to_string())
+            .collect();
+        if !missing_slots.is_empty() {
+            missing_slots.sort();
+            errors.push(error(
+                args_node,
+                format!(
+                    "template `{}` requires a value for {}",
+                    link.template_id,
+                    missing_slots.join(", ")
+                ),
+            ));
+        }
+        if vals.len() != expected_slots.len() {
+            continue;
+        }
+
+        let link_id = PolicyId::new(&link.link_id);
+        match pset.link(template_id, link_id.clone(), vals) {
+            Ok(()) => {
+                link_nodes.insert(link_id, args_node);
+            }
+            Err(e) => errors.push(error(
+                link_id_node,
+                match e.source() {
+                    Some(source) => format!("{e}: {source}"),
+                    None => e.to_string(),
+                },
+            )),
+        }
+    }
+
+    let result = Validator::new(schema).validate(&pset, ValidationMode::Strict);
+    result.validation_errors().for_each(|e| {
+        if let Some(args_node) = link_nodes.get(e.policy_id()) {
+            errors.push(ValidateMessage::new(
+                e.to_string(),
+                input_links_str,
+                args_node.offset,
+                args_node.length,
+            ));
+        }
+    });
+
+    ValidateTemplateLinksResult {
+        success: errors.is_empty(),
+        links: Some(link_nodes.len()),
+        errors: if errors.is_empty() {
+            None
+        } else {
+            Some(errors)
+        },
+    }
+}
+
+/// Link the templates in `input_policies_str` as `input_links_str` describes
+/// and validate the linked policies against the schema. Like the other
+/// validators this is exported once for each schema format, with the schema
+/// first, rather than as a single `validateTemplateLinks(policies, links,
+/// schema)` that would have to guess the format of the schema.
+#[wasm_bindgen(js_name = validateTemplateLinksSchemaJSON)]
+pub fn validate_template_links_schema_json(
+    input_schema_str: &str,
+    input_policies_str: &str,
+    input_links_str: &str,
+) -> ValidateTemplateLinksResult {
+    match Schema::from_json_str(input_schema_str) {
+        Ok(schema) => validate_template_links(schema, input_policies_str, input_links_str),
+        Err(e) => create_error_result(e),
+    }
+}
+
+#[wasm_bindgen(js_name = validateTemplateLinksSchemaCedar)]
+pub fn validate_template_links_schema_cedar(
+    input_schema_str: &str,
+    input_policies_str: &str,
+    input_links_str: &str,
+) -> ValidateTemplateLinksResult {
+    match Schema::from_cedarschema_str(input_schema_str) {
+        Ok((schema, _warnings)) => {
+            validate_template_links(schema, input_policies_str, input_links_str)
+        }
+        Err(e) => create_error_result(e),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+entity User;
+entity Document;
+action view appliesTo { principal: [User], resource: [Document] };
+"#;
+
+    const TEMPLATES: &str = r#"
+@id("viewer")
+permit (principal == ?principal, action == Action::"view", resource in ?resource);
+"#;
+
+    #[test]
+    fn validate_template_links_passes() {
+        let result = validate_template_links_schema_cedar(
+            SCHEMA,
+            TEMPLATES,
+            r#"[{ "template_id": "viewer", "link_id": "alice", "args": { "?principal": "User::\"alice\"", "?resource": "Document::\"doc\"" } }]"#,
+        );
+        assert!(result.success, "{:?}", result.errors);
+        assert_eq!(result.links, Some(1));
+    }
+
+    #[test]
+    fn validate_template_links_reports_positions() {
+        let links = r#"[
+  { "template_id": "missing", "link_id": "a", "args": { "?principal": "User::\"a\"" } },
+  { "template_id": "viewer", "link_id": "b", "args": { "?principal": "User::\"b\"" } },
+  { "template_id": "viewer", "link_id": "c", "args": { "?principal": "Document::\"c\"", "?resource": "Document::\"c\"" } }
+]"#;
+        let result = validate_template_links_schema_cedar(SCHEMA, TEMPLATES, links);
+        assert!(!result.success);
+        let errors = result.errors.unwrap();
+        assert_eq!(errors.len(), 3);
+        assert_eq!(
+            &links[errors[0].offset..][..errors[0].length],
+            "\"missing\""
+        );
+        assert_eq!(errors[1].start_line, 2);
+        assert!(errors[1].message.contains("?resource"));
+        assert_eq!(errors[2].start_line, 3);
+    }
+}
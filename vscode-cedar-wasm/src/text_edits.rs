@@ -0,0 +1,180 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::{Deserialize, Serialize};
+
+use crate::validate_message::SourceRange;
+
+/// Replace the text in `range` with `new_text`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TextEdit {
+    pub range: SourceRange,
+    pub new_text: String,
+}
+
+/// Texts that differ in more lines than this are replaced in one edit rather
+/// than diffed, to bound the time and memory spent diffing
+const MAX_CHANGED_LINES: usize = 2000;
+
+/// Pairs of indexes of equal lines in `a` and `b`, in order, found with the
+/// Myers diff algorithm, or `None` when more than `MAX_CHANGED_LINES` lines
+/// differ
+fn matching_lines(a: &[&str], b: &[&str]) -> Option<Vec<(usize, usize)>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let limit = (n + m).min(MAX_CHANGED_LINES as isize);
+    let offset = limit + 1;
+    let index = |k: isize| (offset + k) as usize;
+    // furthest x reached on each diagonal k = x - y
+    let mut v = vec![0isize; index(limit + 1) + 1];
+    // the part of `v` in use after each step, for finding the path back
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+    for d in 0..=limit {
+        let mut done = false;
+        for k in (-d..=d).step_by(2) {
+            let down = k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]);
+            let mut x = if down {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                done = true;
+                break;
+            }
+        }
+        trace.push(v[index(-d)..=index(d)].to_vec());
+        if done {
+            return Some(backtrack(&trace, n, m));
+        }
+    }
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for d in (1..trace.len() as isize).rev() {
+        let prev = &trace[(d - 1) as usize];
+        let at = |k: isize| prev[(k + d - 1) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            pairs.push((x as usize, y as usize));
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    while x > 0 && y > 0 {
+        x -= 1;
+        y -= 1;
+        pairs.push((x as usize, y as usize));
+    }
+    pairs.reverse();
+    pairs
+}
+
+/// Byte offset of the start of each line in `lines`, and of the end
+fn line_offsets(lines: &[&str]) -> Vec<usize> {
+    let mut offsets = vec![0];
+    for line in lines {
+        offsets.push(offsets[offsets.len() - 1] + line.len());
+    }
+    offsets
+}
+
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.chars()
+        .zip(b.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum()
+}
+
+fn common_suffix_len(a: &str, b: &str) -> usize {
+    a.chars()
+        .rev()
+        .zip(b.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum()
+}
+
+/// Edits to `src` that replace the text between `start` and `end` with
+/// `new_text`, touching only the lines that differ and within them only the
+/// characters that differ
+pub fn diff_edits(src: &str, start: usize, end: usize, new_text: &str) -> Vec<TextEdit> {
+    let old_lines: Vec<&str> = src[start..end].split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new_text.split_inclusive('\n').collect();
+    let old_offsets = line_offsets(&old_lines);
+    let new_offsets = line_offsets(&new_lines);
+    let mut pairs = matching_lines(&old_lines, &new_lines).unwrap_or_default();
+    pairs.push((old_lines.len(), new_lines.len()));
+
+    let mut edits = Vec::new();
+    let (mut old_line, mut new_line) = (0, 0);
+    for (old_match, new_match) in pairs {
+        if old_match > old_line || new_match > new_line {
+            let old = &src[start..end][old_offsets[old_line]..old_offsets[old_match]];
+            let new = &new_text[new_offsets[new_line]..new_offsets[new_match]];
+            let prefix = common_prefix_len(old, new);
+            let suffix = common_suffix_len(&old[prefix..], &new[prefix..]);
+            edits.push(TextEdit {
+                range: SourceRange::new(
+                    src,
+                    start + old_offsets[old_line] + prefix,
+                    old.len() - prefix - suffix,
+                ),
+                new_text: new[prefix..new.len() - suffix].to_string(),
+            });
+        }
+        (old_line, new_line) = (old_match + 1, new_match + 1);
+    }
+    edits
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn apply(src: &str, edits: &[TextEdit]) -> String {
+        let mut result = src.to_string();
+        for edit in edits.iter().rev() {
+            result.replace_range(
+                edit.range.offset..edit.range.offset + edit.range.length,
+                &edit.new_text,
+            );
+        }
+        result
+    }
+
+    #[test]
+    fn diff_edits_touches_only_changes() {
+        let src = "permit(principal, action, resource);\n// unchanged\nforbid (principal, action, resource)\nwhen {  true };\n";
+        let new_text = "permit (principal, action, resource);\n// unchanged\nforbid (principal, action, resource)\nwhen { true };\n";
+        let edits = diff_edits(src, 0, src.len(), new_text);
+        assert_eq!(edits.len(), 2);
+        assert_eq!((edits[0].range.offset, edits[0].range.length), (6, 0));
+        assert_eq!(edits[0].new_text, " ");
+        assert_eq!(edits[1].range.start_line, 3);
+        assert_eq!(apply(src, &edits), new_text);
+
+        assert!(diff_edits(src, 0, src.len(), src).is_empty());
+        let lines = "a\nb\nc\nd\n";
+        let edits = diff_edits(lines, 2, 6, "x\nc\ny\n");
+        assert_eq!(apply(lines, &edits), "a\nx\nc\ny\nd\n");
+    }
+}
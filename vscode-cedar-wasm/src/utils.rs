@@ -1,6 +1,7 @@
 // Copyright Cedar Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen(js_name = "setPanicHook")]
@@ -19,3 +20,8 @@ pub fn set_panic_hook() {
 pub fn get_cedar_sdk_version() -> String {
     std::env!("CEDAR_VERSION").to_string()
 }
+
+/// Convert nested result data to plain JavaScript objects by way of JSON
+pub(crate) fn to_js_value<T: Serialize>(value: &T) -> JsValue {
+    js_sys::JSON::parse(&serde_json::to_string(value).unwrap()).unwrap()
+}
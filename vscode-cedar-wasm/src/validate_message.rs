@@ -1,6 +1,7 @@
 // Copyright Cedar Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use miette::{Diagnostic, LabeledSpan};
 use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
@@ -10,8 +11,15 @@ export class ValidateMessage {
   readonly message: string;
   readonly length: number;
   readonly offset: number;
+  readonly startLine: number;
+  readonly startCharacter: number;
+  readonly endLine: number;
+  readonly endCharacter: number;
 }"#;
 
+/// `offset` and `length` are the byte span reported by Cedar, the line and
+/// character positions are zero-based and count UTF-16 code units to match
+/// `vscode.Position`
 #[wasm_bindgen(getter_with_clone, skip_typescript)]
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ValidateMessage {
@@ -21,11 +29,151 @@ pub struct ValidateMessage {
     pub offset: usize,
     #[wasm_bindgen(readonly)]
     pub length: usize,
+    #[wasm_bindgen(readonly, js_name = startLine)]
+    pub start_line: usize,
+    #[wasm_bindgen(readonly, js_name = startCharacter)]
+    pub start_character: usize,
+    #[wasm_bindgen(readonly, js_name = endLine)]
+    pub end_line: usize,
+    #[wasm_bindgen(readonly, js_name = endCharacter)]
+    pub end_character: usize,
 }
 
-pub fn convert_messages_to_js_array(messages: &Vec<ValidateMessage>) -> js_sys::Array {
+impl ValidateMessage {
+    /// Message for the byte span `offset..offset + length` of `src`
+    pub fn new(message: String, src: &str, offset: usize, length: usize) -> Self {
+        let (start_line, start_character) = utf16_position(src, offset);
+        let (end_line, end_character) = utf16_position(src, offset.saturating_add(length));
+        ValidateMessage {
+            message,
+            offset,
+            length,
+            start_line,
+            start_character,
+            end_line,
+            end_character,
+        }
+    }
+
+    /// Message without a known location, reported at the start of the document
+    pub fn without_span(message: String) -> Self {
+        ValidateMessage {
+            message,
+            offset: 0,
+            length: 0,
+            start_line: 0,
+            start_character: 0,
+            end_line: 0,
+            end_character: 0,
+        }
+    }
+}
+
+/// Byte span of `src` along with the same zero-based UTF-16 positions as
+/// `ValidateMessage`, for results that locate source text rather than errors
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceRange {
+    pub offset: usize,
+    pub length: usize,
+    pub start_line: usize,
+    pub start_character: usize,
+    pub end_line: usize,
+    pub end_character: usize,
+}
+
+impl SourceRange {
+    pub fn new(src: &str, offset: usize, length: usize) -> Self {
+        let (start_line, start_character) = utf16_position(src, offset);
+        let (end_line, end_character) = utf16_position(src, offset.saturating_add(length));
+        SourceRange {
+            offset,
+            length,
+            start_line,
+            start_character,
+            end_line,
+            end_character,
+        }
+    }
+}
+
+/// Convert a byte offset into `src` to a zero-based line and UTF-16 character,
+/// clamping offsets past the end or inside a multi-byte character
+pub fn utf16_position(src: &str, offset: usize) -> (usize, usize) {
+    let mut offset = offset.min(src.len());
+    while !src.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &src[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count();
+    let character = before[line_start..].encode_utf16().count();
+    (line, character)
+}
+
+/// Text of `diagnostic` followed by the text of one of its labels and its help
+pub fn format_diagnostic(diagnostic: &dyn Diagnostic, label: Option<&str>) -> String {
+    match (label, diagnostic.help()) {
+        (None, None) => diagnostic.to_string(),
+        (None, Some(help)) => format!("{diagnostic}\n{help}"),
+        (Some(label), None) => format!("{diagnostic}\n{label}"),
+        (Some(label), Some(help)) => format!("{diagnostic}\n{label}\n{help}"),
+    }
+}
+
+/// One message for each labeled span of `diagnostic` in `src`, or a single
+/// message without a location that includes the error source when there are
+/// no labels to point at
+pub fn messages_from_diagnostic(diagnostic: &dyn Diagnostic, src: &str) -> Vec<ValidateMessage> {
+    messages_from_labels(diagnostic, src, |labeled_span| {
+        Some((labeled_span.offset(), labeled_span.len()))
+    })
+}
+
+/// Like `messages_from_diagnostic`, for diagnostics whose labels are not
+/// offsets into `src`, with `span` finding the offset and length of each label
+/// in `src` and the message left without a location when it cannot
+pub fn messages_from_labels(
+    diagnostic: &dyn Diagnostic,
+    src: &str,
+    span: impl Fn(&LabeledSpan) -> Option<(usize, usize)>,
+) -> Vec<ValidateMessage> {
+    let messages: Vec<ValidateMessage> = diagnostic
+        .labels()
+        .into_iter()
+        .flatten()
+        .map(|labeled_span| {
+            let message = format_diagnostic(diagnostic, labeled_span.label());
+            match span(&labeled_span) {
+                Some((offset, length)) => ValidateMessage::new(message, src, offset, length),
+                None => ValidateMessage::without_span(message),
+            }
+        })
+        .collect();
+    if !messages.is_empty() {
+        return messages;
+    }
+    vec![ValidateMessage::without_span(diagnostic_message(
+        diagnostic,
+    ))]
+}
+
+/// Text of `diagnostic` followed by its error source and help, for messages
+/// that are not tied to a label
+pub fn diagnostic_message(diagnostic: &dyn Diagnostic) -> String {
+    let message = match diagnostic.source() {
+        Some(source) => format!("{diagnostic}: {source}"),
+        None => diagnostic.to_string(),
+    };
+    match diagnostic.help() {
+        Some(help) => format!("{message}\n{help}"),
+        None => message,
+    }
+}
+
+pub fn convert_messages_to_js_array(messages: &[ValidateMessage]) -> js_sys::Array {
     let arr = js_sys::Array::new_with_length(messages.len() as u32);
-    
+
     for (i, e) in messages.iter().enumerate() {
         let vm = js_sys::Object::new();
         js_sys::Reflect::set(
@@ -46,9 +194,49 @@ pub fn convert_messages_to_js_array(messages: &Vec<ValidateMessage>) -> js_sys::
             &JsValue::from_f64(e.length as f64),
         )
         .unwrap();
+        js_sys::Reflect::set(
+            &vm,
+            &JsValue::from_str("startLine"),
+            &JsValue::from_f64(e.start_line as f64),
+        )
+        .unwrap();
+        js_sys::Reflect::set(
+            &vm,
+            &JsValue::from_str("startCharacter"),
+            &JsValue::from_f64(e.start_character as f64),
+        )
+        .unwrap();
+        js_sys::Reflect::set(
+            &vm,
+            &JsValue::from_str("endLine"),
+            &JsValue::from_f64(e.end_line as f64),
+        )
+        .unwrap();
+        js_sys::Reflect::set(
+            &vm,
+            &JsValue::from_str("endCharacter"),
+            &JsValue::from_f64(e.end_character as f64),
+        )
+        .unwrap();
 
         arr.set(i as u32, JsValue::from(vm));
     }
-    
+
     arr
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn utf16_position_counts_surrogate_pairs() {
+        let src = "@id(\"🌲\")\npermit(principal == User::\"Zoë\", action, resource);";
+        let offset = src.find("Zoë").unwrap();
+        let message = ValidateMessage::new(String::from("test"), src, offset, "Zoë".len());
+        assert_eq!((message.start_line, message.start_character), (1, 27));
+        assert_eq!((message.end_line, message.end_character), (1, 30));
+        assert_eq!(utf16_position("\"🌲\"", 5), (0, 3));
+        assert_eq!(utf16_position("a\nb", 100), (1, 1));
+    }
+}
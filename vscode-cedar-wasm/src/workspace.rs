@@ -0,0 +1,311 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use cedar_policy::{Entities, PolicySet, Schema, Validator};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+use crate::entities_validator::{self, entities_result, parse_entities, ValidateEntitiesResult};
+use crate::entity_checker::EntityError;
+use crate::policy_validator::{
+    self, parse_validation_mode, validate_parsed_policies, ValidatePolicyResult,
+};
+use crate::schema_validator::{parse_schema_cedar, parse_schema_json, ValidateSchemaResult};
+use crate::syntax_validator::{parse_policies, syntax_result, ValidateSyntaxResult};
+use crate::validate_message::ValidateMessage;
+
+struct SchemaDocument {
+    /// Incremented on every schema update so cached entities know to re-parse
+    generation: u64,
+    validator: Option<Validator>,
+}
+
+struct PoliciesDocument {
+    text: String,
+    parse_result: Result<PolicySet, Vec<ValidateMessage>>,
+}
+
+struct EntitiesDocument {
+    text: String,
+    /// Entities parsed against the schema with this generation
+    parsed: Option<(u64, Result<Entities, Vec<EntityError>>)>,
+}
+
+/// Parsed schemas, policy sets and entities keyed by document URI, so that
+/// validating a document does not re-parse the documents it depends on
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct CedarWorkspace {
+    generation: u64,
+    schemas: HashMap<String, SchemaDocument>,
+    policies: HashMap<String, PoliciesDocument>,
+    entities: HashMap<String, EntitiesDocument>,
+}
+
+impl CedarWorkspace {
+    fn store_schema(&mut self, uri: String, schema: Option<Schema>) {
+        self.generation += 1;
+        self.schemas.insert(
+            uri,
+            SchemaDocument {
+                generation: self.generation,
+                validator: schema.map(Validator::new),
+            },
+        );
+    }
+}
+
+/// Validator for the schema stored for `schema_uri` along with its generation
+fn stored_validator<'a>(
+    schemas: &'a HashMap<String, SchemaDocument>,
+    schema_uri: &str,
+) -> Result<(u64, &'a Validator), String> {
+    match schemas.get(schema_uri) {
+        Some(SchemaDocument {
+            generation,
+            validator: Some(validator),
+        }) => Ok((*generation, validator)),
+        Some(_) => Err(format!("schema `{schema_uri}` could not be parsed")),
+        None => Err(format!("schema `{schema_uri}` has not been loaded")),
+    }
+}
+
+#[wasm_bindgen]
+impl CedarWorkspace {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> CedarWorkspace {
+        CedarWorkspace::default()
+    }
+
+    #[wasm_bindgen(js_name = updateSchemaJSON)]
+    pub fn update_schema_json(
+        &mut self,
+        uri: String,
+        input_schema_str: &str,
+    ) -> ValidateSchemaResult {
+        let (result, schema) = parse_schema_json(input_schema_str);
+        self.store_schema(uri, schema);
+        result
+    }
+
+    #[wasm_bindgen(js_name = updateSchemaCedar)]
+    pub fn update_schema_cedar(
+        &mut self,
+        uri: String,
+        input_schema_str: &str,
+    ) -> ValidateSchemaResult {
+        let (result, schema) = parse_schema_cedar(input_schema_str);
+        self.store_schema(uri, schema);
+        result
+    }
+
+    /// Parse and store the policies of `uri`, replacing any stored for an
+    /// earlier version of the document
+    #[wasm_bindgen(js_name = updatePolicies)]
+    pub fn update_policies(
+        &mut self,
+        uri: String,
+        input_policies_str: String,
+    ) -> ValidateSyntaxResult {
+        let parse_result = parse_policies(&input_policies_str);
+        let result = syntax_result(&parse_result);
+        self.policies.insert(
+            uri,
+            PoliciesDocument {
+                text: input_policies_str,
+                parse_result,
+            },
+        );
+        result
+    }
+
+    /// Entities are parsed on the next `validateEntities` call, since how they
+    /// parse depends on the schema they are validated against
+    #[wasm_bindgen(js_name = updateEntities)]
+    pub fn update_entities(&mut self, uri: String, input_entities_str: String) {
+        self.entities.insert(
+            uri,
+            EntitiesDocument {
+                text: input_entities_str,
+                parsed: None,
+            },
+        );
+    }
+
+    /// Forget the schema, policies or entities stored for `uri`
+    pub fn remove(&mut self, uri: &str) {
+        self.schemas.remove(uri);
+        self.policies.remove(uri);
+        self.entities.remove(uri);
+    }
+
+    /// Validate the policies stored for `policies_uri` against a stored schema
+    #[wasm_bindgen(js_name = validatePolicies)]
+    pub fn validate_policies(
+        &self,
+        schema_uri: &str,
+        policies_uri: &str,
+        validation_mode: Option<String>,
+    ) -> ValidatePolicyResult {
+        let mode = match parse_validation_mode(validation_mode) {
+            Ok(mode) => mode,
+            Err(e) => return policy_validator::create_error_result(None, e),
+        };
+        let Some(policies_doc) = self.policies.get(policies_uri) else {
+            return policy_validator::create_error_result(
+                Some(mode),
+                format!("policies `{policies_uri}` have not been loaded"),
+            );
+        };
+        match stored_validator(&self.schemas, schema_uri) {
+            Ok((_, validator)) => validate_parsed_policies(
+                validator,
+                &policies_doc.parse_result,
+                &policies_doc.text,
+                mode,
+            ),
+            Err(e) => policy_validator::create_error_result(Some(mode), e),
+        }
+    }
+
+    /// Validate policies that are not stored in the workspace, such as a
+    /// policy generated to probe the schema, against a stored schema
+    #[wasm_bindgen(js_name = validatePolicyText)]
+    pub fn validate_policy_text(
+        &self,
+        schema_uri: &str,
+        input_policies_str: &str,
+        validation_mode: Option<String>,
+    ) -> ValidatePolicyResult {
+        let mode = match parse_validation_mode(validation_mode) {
+            Ok(mode) => mode,
+            Err(e) => return policy_validator::create_error_result(None, e),
+        };
+        match stored_validator(&self.schemas, schema_uri) {
+            Ok((_, validator)) => validate_parsed_policies(
+                validator,
+                &parse_policies(input_policies_str),
+                input_policies_str,
+                mode,
+            ),
+            Err(e) => policy_validator::create_error_result(Some(mode), e),
+        }
+    }
+
+    #[wasm_bindgen(js_name = validateEntities)]
+    pub fn validate_entities(
+        &mut self,
+        schema_uri: &str,
+        entities_uri: &str,
+    ) -> ValidateEntitiesResult {
+        let (generation, validator) = match stored_validator(&self.schemas, schema_uri) {
+            Ok(schema) => schema,
+            Err(e) => return entities_validator::create_error_result(e),
+        };
+        let Some(entities_doc) = self.entities.get_mut(entities_uri) else {
+            return entities_validator::create_error_result(format!(
+                "entities `{entities_uri}` have not been loaded"
+            ));
+        };
+        match &entities_doc.parsed {
+            Some((parsed_generation, parse_result)) if *parsed_generation == generation => {
+                entities_result(parse_result)
+            }
+            _ => {
+                let parse_result = parse_entities(&entities_doc.text, Some(validator.schema()));
+                let result = entities_result(&parse_result);
+                entities_doc.parsed = Some((generation, parse_result));
+                result
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCHEMA: &str = r#"
+entity User { jobLevel: Long };
+entity Document;
+action view appliesTo { principal: [User], resource: [Document] };
+"#;
+
+    #[test]
+    fn workspace_validates_against_stored_schema() {
+        let mut workspace = CedarWorkspace::new();
+        assert!(
+            workspace
+                .update_schema_cedar(String::from("schema"), SCHEMA)
+                .success
+        );
+        let policy = "permit(principal, action == Action::\"view\", resource) when { principal.jobLevel > 3 };";
+        assert!(
+            workspace
+                .validate_policy_text("schema", policy, None)
+                .success
+        );
+        assert!(
+            !workspace
+                .validate_policy_text(
+                    "schema",
+                    "permit(principal is Photo, action, resource);",
+                    None
+                )
+                .success
+        );
+        let missing = workspace.validate_policy_text("other", policy, None);
+        assert!(!missing.success);
+
+        assert!(
+            workspace
+                .update_policies(String::from("policies"), String::from(policy))
+                .success
+        );
+        assert!(
+            workspace
+                .validate_policies("schema", "policies", None)
+                .success
+        );
+        let invalid = "permit(principal is Photo, action, resource);";
+        workspace.update_policies(String::from("policies"), String::from(invalid));
+        let result = workspace.validate_policies("schema", "policies", None);
+        assert!(!result.success);
+        assert_eq!(result.policy_diagnostics()[0].labels[0].start_character, 20);
+        workspace.remove("policies");
+        assert!(
+            !workspace
+                .validate_policies("schema", "policies", None)
+                .success
+        );
+
+        workspace.remove("schema");
+        assert!(
+            !workspace
+                .validate_policy_text("schema", policy, None)
+                .success
+        );
+    }
+
+    #[test]
+    fn workspace_revalidates_entities_after_schema_update() {
+        let mut workspace = CedarWorkspace::new();
+        workspace.update_schema_cedar(String::from("schema"), SCHEMA);
+        workspace.update_entities(
+            String::from("entities"),
+            String::from(r#"[{ "uid": { "type": "User", "id": "alice" }, "attrs": { "jobLevel": 7 }, "parents": [] }]"#),
+        );
+        assert!(workspace.validate_entities("schema", "entities").success);
+        assert!(workspace.validate_entities("schema", "entities").success);
+
+        workspace.update_schema_cedar(String::from("schema"), "entity User { name: String };");
+        assert!(!workspace.validate_entities("schema", "entities").success);
+
+        assert!(
+            !workspace
+                .update_schema_cedar(String::from("schema"), "entity")
+                .success
+        );
+        assert!(!workspace.validate_entities("schema", "entities").success);
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright Cedar Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![cfg(feature = "lsp")]
+
+use serde_json::{json, Value};
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const SCHEMA: &str = r#"entity User { jobLevel: Long };
+entity Document;
+action view appliesTo { principal: User, resource: Document };
+"#;
+
+/// A running `cedar-language-server` driven over its stdin and stdout
+struct Client {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl Client {
+    /// Start a server without initializing it
+    fn spawn() -> Self {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_cedar-language-server"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Client {
+            child,
+            stdin,
+            stdout,
+            next_id: 0,
+        }
+    }
+
+    fn start(root: &str) -> Self {
+        let mut client = Client::spawn();
+        let initialized = client.request("initialize", json!({ "rootUri": root }));
+        assert_eq!(
+            initialized["result"]["capabilities"]["documentFormattingProvider"],
+            true
+        );
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    fn send(&mut self, message: Value) {
+        let body = message.to_string();
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            self.stdout.read_line(&mut line).unwrap();
+            match line.trim_end().split_once(": ") {
+                Some(("Content-Length", value)) => length = value.parse().unwrap(),
+                _ if line.trim_end().is_empty() => break,
+                _ => {}
+            }
+        }
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }));
+        let response = self.receive();
+        assert_eq!(response["id"], id);
+        response
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+
+    fn open(&mut self, uri: &str, language_id: &str, text: &str) -> Value {
+        self.notify(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": uri, "languageId": language_id, "version": 1, "text": text }
+            }),
+        );
+        self.receive()
+    }
+
+    fn exit(mut self) -> Option<i32> {
+        let response = self.request("shutdown", Value::Null);
+        assert_eq!(response["result"], Value::Null);
+        self.notify("exit", Value::Null);
+        self.child.wait().unwrap().code()
+    }
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cedar-lsp-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn uri(dir: &std::path::Path, file: &str) -> String {
+    format!("file://{}", dir.join(file).display())
+}
+
+#[test]
+fn publishes_diagnostics_for_each_kind_of_document() {
+    let dir = temp_dir("diagnostics");
+    fs::write(dir.join("cedarschema"), SCHEMA).unwrap();
+    let mut client = Client::start(&uri(&dir, ""));
+
+    let policies_uri = uri(&dir, "policies.cedar");
+    let published = client.open(&policies_uri, "cedar", "permit (principal,");
+    assert_eq!(published["method"], "textDocument/publishDiagnostics");
+    assert_eq!(published["params"]["uri"], policies_uri.as_str());
+    assert_eq!(published["params"]["diagnostics"][0]["code"], "syntax");
+
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": policies_uri, "version": 2 },
+            "contentChanges": [{ "text": "permit (principal, action == Action::\"view\", resource)\nwhen { principal.jobLevl > 3 };" }]
+        }),
+    );
+    let diagnostics = &client.receive()["params"]["diagnostics"];
+    assert_eq!(diagnostics[0]["code"], "unsafeAttributeAccess");
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 1);
+
+    let entities = client.open(
+        &uri(&dir, "users.cedarentities.json"),
+        "json",
+        r#"[{ "uid": { "type": "User", "id": "a" }, "attrs": { "jobLevel": "high" }, "parents": [] }]"#,
+    );
+    assert_eq!(entities["params"]["diagnostics"][0]["code"], "entities");
+
+    // fixing the schema in the editor revalidates the open policies against it
+    client.open(&uri(&dir, "cedarschema"), "cedarschema", SCHEMA);
+    for _ in 0..2 {
+        client.receive();
+    }
+    client.notify(
+        "textDocument/didChange",
+        json!({
+            "textDocument": { "uri": uri(&dir, "cedarschema"), "version": 2 },
+            "contentChanges": [{ "text": SCHEMA.replace("jobLevel", "jobLevl") }]
+        }),
+    );
+    let mut published: Vec<Value> = (0..3).map(|_| client.receive()).collect();
+    published.sort_by_key(|message| message["params"]["uri"].to_string());
+    assert_eq!(published[1]["params"]["uri"], policies_uri.as_str());
+    assert_eq!(published[1]["params"]["diagnostics"], json!([]));
+
+    assert_eq!(client.exit(), Some(0));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn formats_documents_and_lists_symbols() {
+    let dir = temp_dir("format");
+    let mut client = Client::start(&uri(&dir, ""));
+    let policies_uri = uri(&dir, "policies.cedar");
+    let text = "@id(\"view\")\npermit(principal,action,resource) when {true};\n";
+    client.open(&policies_uri, "cedar", text);
+
+    let formatted = client.request(
+        "textDocument/formatting",
+        json!({
+            "textDocument": { "uri": policies_uri },
+            "options": { "tabSize": 2, "insertSpaces": true }
+        }),
+    );
+    let edits = formatted["result"].as_array().unwrap();
+    assert!(!edits.is_empty());
+    assert!(edits.iter().all(|edit| edit["range"]["start"]["line"] == 1));
+
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": policies_uri } }),
+    );
+    let policy = &symbols["result"][0];
+    assert_eq!(policy["name"], "view");
+    assert_eq!(policy["children"].as_array().unwrap().len(), 4);
+    assert_eq!(policy["children"][3]["name"], "when");
+
+    let schema_uri = uri(&dir, "cedarschema");
+    // opening a schema republishes every open document
+    client.open(&schema_uri, "cedarschema", "entity  User;\n");
+    client.receive();
+    let formatted = client.request(
+        "textDocument/formatting",
+        json!({
+            "textDocument": { "uri": schema_uri },
+            "options": { "tabSize": 2, "insertSpaces": true }
+        }),
+    );
+    assert_eq!(formatted["result"][0]["newText"], "");
+
+    let unknown = client.request("textDocument/hover", json!({}));
+    assert_eq!(unknown["error"]["code"], -32601);
+    assert_eq!(client.exit(), Some(0));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn rejects_requests_before_initialize() {
+    let dir = temp_dir("uninitialized");
+    let mut client = Client::spawn();
+    let policies_uri = uri(&dir, "policies.cedar");
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": policies_uri } }),
+    );
+    assert_eq!(symbols["error"]["code"], -32002);
+    client.notify(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": { "uri": policies_uri, "languageId": "cedar", "version": 1, "text": "permit (principal," }
+        }),
+    );
+
+    // the document opened before `initialize` was ignored
+    let initialized = client.request("initialize", json!({ "rootUri": uri(&dir, "") }));
+    assert!(initialized["result"]["capabilities"].is_object());
+    let symbols = client.request(
+        "textDocument/documentSymbol",
+        json!({ "textDocument": { "uri": policies_uri } }),
+    );
+    assert_eq!(symbols["error"]["code"], -32602);
+    assert_eq!(client.exit(), Some(0));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn rereads_the_schema_when_it_changes_on_disk() {
+    let dir = temp_dir("schema-cache");
+    fs::write(dir.join("cedarschema"), SCHEMA).unwrap();
+    let mut client = Client::start(&uri(&dir, ""));
+    let policies_uri = uri(&dir, "policies.cedar");
+    let published = client.open(
+        &policies_uri,
+        "cedar",
+        "permit (principal, action == Action::\"view\", resource)\nwhen { principal.jobLevl > 3 };",
+    );
+    assert_eq!(
+        published["params"]["diagnostics"][0]["code"],
+        "unsafeAttributeAccess"
+    );
+
+    fs::write(
+        dir.join("cedarschema"),
+        SCHEMA.replace("jobLevel", "jobLevl"),
+    )
+    .unwrap();
+    client.notify(
+        "workspace/didChangeWatchedFiles",
+        json!({ "changes": [{ "uri": uri(&dir, "cedarschema"), "type": 2 }] }),
+    );
+    let published = client.receive();
+    assert_eq!(published["params"]["uri"], policies_uri.as_str());
+    assert_eq!(published["params"]["diagnostics"], json!([]));
+
+    assert_eq!(client.exit(), Some(0));
+    fs::remove_dir_all(dir).unwrap();
+}